@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    collide::{Collider, CollisionFlags, Contact},
+    navigation::Navigation,
+    player::Player,
+    state::GameState,
+    velocity::Gravity,
+};
+
+pub struct BreakablePlugin;
+
+impl Plugin for BreakablePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(damage_tiles.after("collision"))
+                .with_system(remove_broken_tiles.after(damage_tiles)),
+        )
+        .add_event::<TileBroken>();
+    }
+}
+
+/// The tilemap tile drawn for a collider, so both can be removed together.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct TileRef {
+    pub layer: Entity,
+    pub pos: TilePos,
+}
+
+/// Floor that collapses a while after something stands on it.
+#[derive(Component, Debug)]
+pub struct Crumbling {
+    pub timer: Timer,
+    pub triggered: bool,
+}
+
+/// Wall that breaks when bodies other than the robot hit it hard enough.
+#[derive(Component, Debug)]
+pub struct Breakable {
+    /// Hits left before the wall breaks.
+    pub durability: u32,
+    /// Speed a body has to hit the wall with for the hit to count.
+    pub impact_speed: f32,
+    /// Weight a body needs for its hits to count, so projectiles and light
+    /// boxes bounce off.
+    pub weight: f32,
+}
+
+/// Sent when a breakable or crumbling tile gives way.
+pub struct TileBroken(pub Entity);
+
+/// Speed `velocity` was carrying into the `side` that touched something.
+fn impact_speed(side: CollisionFlags, velocity: Vec3) -> f32 {
+    let mut speed: f32 = 0.0;
+    if side.contains(CollisionFlags::BOTTOM) {
+        speed = speed.max(-velocity.y);
+    }
+    if side.contains(CollisionFlags::TOP) {
+        speed = speed.max(velocity.y);
+    }
+    if side.contains(CollisionFlags::LEFT) {
+        speed = speed.max(-velocity.x);
+    }
+    if side.contains(CollisionFlags::RIGHT) {
+        speed = speed.max(velocity.x);
+    }
+    speed
+}
+
+fn damage_tiles(
+    mut contacts: EventReader<Contact>,
+    mut broken: EventWriter<TileBroken>,
+    mut crumbling: Query<(Entity, &mut Crumbling)>,
+    mut breakable: Query<&mut Breakable>,
+    bodies: Query<(&Collider, Option<&Gravity>, Option<&Player>)>,
+    time: Res<Time>,
+) {
+    for contact in contacts.iter() {
+        let (collider, gravity, player) = match bodies.get(contact.entity) {
+            Ok(body) => body,
+            _ => continue,
+        };
+        if let Ok((_, mut crumbling)) = crumbling.get_mut(contact.other) {
+            let floor = gravity.copied().unwrap_or_default().floor();
+            if contact.side.contains(floor) {
+                crumbling.triggered = true;
+            }
+        }
+        if let Ok(mut breakable) = breakable.get_mut(contact.other) {
+            if player.is_none()
+                && collider.weight() >= breakable.weight
+                && breakable.durability > 0
+                && impact_speed(contact.side, contact.velocity) >= breakable.impact_speed
+            {
+                breakable.durability -= 1;
+                if breakable.durability == 0 {
+                    broken.send(TileBroken(contact.other));
+                }
+            }
+        }
+    }
+
+    for (entity, mut crumbling) in crumbling.iter_mut() {
+        if !crumbling.triggered || crumbling.timer.finished() {
+            continue;
+        }
+        crumbling.timer.tick(time.delta());
+        if crumbling.timer.just_finished() {
+            broken.send(TileBroken(entity));
+        }
+    }
+}
+
+fn remove_broken_tiles(
+    mut commands: Commands,
+    mut broken: EventReader<TileBroken>,
+    broken_tiles: Query<(&Transform, &Collider, &TileRef)>,
+    layers: Query<&Layer>,
+    mut chunks: Query<&mut Chunk>,
+    mut tiles: Query<&mut Tile>,
+    mut navigation: Option<ResMut<Navigation>>,
+) {
+    let mut broken: Vec<Entity> = broken.iter().map(|event| event.0).collect();
+    // a tile can crumble and get broken in the same frame
+    broken.sort();
+    broken.dedup();
+    if broken.is_empty() {
+        return;
+    }
+    for entity in broken {
+        commands.entity(entity).despawn();
+        let (transform, collider, tile_ref) = match broken_tiles.get(entity) {
+            Ok(tile) => tile,
+            _ => continue,
+        };
+        if let Some(navigation) = navigation.as_mut() {
+            for cell in Navigation::covered(transform.translation, collider.size) {
+                navigation.set_free(Navigation::center(cell));
+            }
+        }
+        // the tile is hidden rather than despawned so restarting the level can
+        // still despawn the whole map, which brings it back
+        let layer = match layers.get(tile_ref.layer) {
+            Ok(layer) => layer,
+            _ => continue,
+        };
+        let chunk_size = layer.settings.chunk_size;
+        let chunk_pos = ChunkPos(tile_ref.pos.0 / chunk_size.0, tile_ref.pos.1 / chunk_size.1);
+        let mut chunk = match layer.get_chunk(chunk_pos).map(|e| chunks.get_mut(e)) {
+            Some(Ok(chunk)) => chunk,
+            _ => continue,
+        };
+        let tile_entity = chunk
+            .to_chunk_pos(tile_ref.pos)
+            .ok()
+            .and_then(|pos| chunk.get_tile_entity(pos));
+        if let Some(Ok(mut tile)) = tile_entity.map(|e| tiles.get_mut(e)) {
+            tile.visible = false;
+            chunk.needs_remesh = true;
+        }
+    }
+    if let Some(navigation) = navigation.as_mut() {
+        navigation.connect();
+    }
+}
@@ -1,6 +1,9 @@
 use bevy::{prelude::*, render::primitives::Frustum};
 
-use crate::{player::Player, state::GameState};
+use crate::{
+    player::{Active, Player},
+    state::GameState,
+};
 
 pub struct CameraPlugin;
 
@@ -13,6 +16,9 @@ impl Plugin for CameraPlugin {
 #[derive(Component)]
 pub struct CameraAnchor;
 
+/// The robot the camera follows, or the enemy it hacked.
+type ActiveRobot = (With<Active>, Without<Camera>, Without<CameraAnchor>);
+
 fn move_camera(
     mut camera_query: Query<
         &mut Transform,
@@ -24,7 +30,7 @@ fn move_camera(
         ),
     >,
     camera_anchors: Query<&Transform, With<CameraAnchor>>,
-    player_query: Query<&Transform, (With<Player>, Without<CameraAnchor>)>,
+    player_query: Query<&Transform, ActiveRobot>,
 ) {
     let player = match player_query.get_single() {
         Ok(p) => p,
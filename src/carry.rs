@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use crate::{state::GameState, velocity::Velocity};
+
+pub struct CarryPlugin;
+
+impl Plugin for CarryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(carry_bodies.after("gravity").before("collision")),
+        );
+    }
+}
+
+/// Body that can be picked up and carried around.
+#[derive(Component, Debug)]
+pub struct Carryable;
+
+/// Body being held by `carrier`, kept at `offset` from it.
+#[derive(Component, Debug)]
+pub struct Carried {
+    pub carrier: Entity,
+    pub offset: Vec3,
+}
+
+/// Moves carried bodies to where their carrier holds them. They are moved by
+/// velocity rather than teleported so they still can't pass through walls.
+fn carry_bodies(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut carried: Query<(Entity, &Carried, &Transform, &mut Velocity)>,
+    carriers: Query<(&Transform, Option<&Velocity>), Without<Carried>>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, carried, transform, mut velocity) in carried.iter_mut() {
+        let (carrier, carrier_velocity) = match carriers.get(carried.carrier) {
+            Ok(carrier) => carrier,
+            _ => {
+                commands.entity(entity).remove::<Carried>();
+                continue;
+            }
+        };
+        // carried bodies don't fall
+        velocity.force = Vec3::ZERO;
+        if delta > 0.0 {
+            // keep up with where the carrier is about to move to
+            let carrier_linvel = carrier_velocity.map_or(Vec3::ZERO, |v| v.linvel);
+            let target = carrier.translation + carrier_linvel * delta + carried.offset;
+            velocity.linvel = (target - transform.translation).truncate().extend(0.0) / delta;
+        }
+    }
+}
@@ -8,14 +8,18 @@ use bevy::{
 use bitflags::bitflags;
 
 use crate::{
-    map::BLOCK_SIZE, player::Player, state::GameState, tiled_loader::TiledMap, velocity::Velocity,
+    map::BLOCK_SIZE,
+    player::Player,
+    state::GameState,
+    tiled_loader::TiledMap,
+    velocity::{Gravity, Velocity},
 };
 
 pub struct CollidePlugin;
 
 impl Plugin for CollidePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
+        app.add_event::<Contact>().add_system_set(
             SystemSet::on_update(GameState::Play)
                 .with_system(handle_collisions)
                 .label("collision"),
@@ -59,7 +63,7 @@ pub struct Collider {
 }
 
 impl Collider {
-    fn weight(&self) -> f32 {
+    pub fn weight(&self) -> f32 {
         match self.kind {
             ColliderKind::Movable(w) => w,
             ColliderKind::Death => f32::INFINITY,
@@ -70,6 +74,24 @@ impl Collider {
     }
 }
 
+/// Material of a static collider, felt by bodies standing on top of it.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Surface {
+    /// Scales the drag along it and traction of bodies standing on it.
+    pub friction: f32,
+    /// Speed along it that bodies standing on it are carried at.
+    pub velocity: f32,
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Self {
+            friction: 1.0,
+            velocity: 0.0,
+        }
+    }
+}
+
 #[derive(Eq, PartialEq)]
 enum Axis {
     X,
@@ -95,237 +117,337 @@ impl From<usize> for Axis {
     }
 }
 
+/// Furthest a body may travel in a single sub-step. This is kept below half
+/// the height of the thinnest collider (spikes) so fast bodies can't skip
+/// past a contact between two sub-steps.
+const MAX_STEP: f32 = BLOCK_SIZE / 12.0;
+/// Most sub-steps a single step is split into, so a runaway velocity or a
+/// long frame hitch can't stall the game. Bodies only move further than
+/// [`MAX_STEP`] per sub-step beyond this.
+const MAX_STEPS: usize = 64;
+
+/// A collider as seen by the physics step. Bodies are copied out of the world,
+/// stepped, and copied back so the step can also run on a cloned world.
+#[derive(Clone)]
+pub struct Body {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub collider: Collider,
+    pub velocity: Option<Velocity>,
+    pub surface: Option<Surface>,
+    /// Decides which side of the body it stands on.
+    pub gravity: Option<Gravity>,
+}
+
+/// `entity` touched `other` on its `side` during a physics step.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub entity: Entity,
+    pub other: Entity,
+    pub side: CollisionFlags,
+    pub kind: ColliderKind,
+    /// Velocity `entity` was moving at when the physics step started.
+    pub velocity: Vec3,
+}
+
+type BodyQuery<'a> = (
+    Entity,
+    &'a mut Transform,
+    &'a mut Collider,
+    Option<&'a mut Velocity>,
+    Option<&'a Surface>,
+    Option<&'a Gravity>,
+);
+
 fn handle_collisions(
     mut events: EventWriter<GameEvent>,
-    mut colliders: Query<(Entity, &mut Transform, &mut Collider)>,
-    player_entity: Query<Entity, With<Player>>,
-    mut velocity_query: Query<&mut Velocity>,
+    mut contact_events: EventWriter<Contact>,
+    mut colliders: Query<BodyQuery>,
+    players: Query<Entity, With<Player>>,
     time: Res<Time>,
     assets: Res<Assets<TiledMap>>,
     map_query: Query<&Handle<TiledMap>>,
 ) {
     let dimensions = match map_query.get_single() {
-        Ok(handle) => assets
-            .get(handle)
-            .map(|tm| {
-                (
-                    tm.map.width as f32 * BLOCK_SIZE,
-                    tm.map.height as f32 * BLOCK_SIZE,
-                )
-            })
-            .unwrap_or((16.0 * BLOCK_SIZE, 16.0 * BLOCK_SIZE)),
+        Ok(handle) => map_dimensions(assets.get(handle)),
         _ => return,
     };
 
-    let player_entity = match player_entity.get_single() {
-        Ok(e) => e,
-        _ => return,
-    };
+    if players.is_empty() {
+        return;
+    }
+
+    let mut bodies: Vec<Body> = colliders
+        .iter()
+        .map(
+            |(entity, transform, collider, velocity, surface, gravity)| Body {
+                entity,
+                position: transform.translation,
+                collider: *collider,
+                velocity: velocity.cloned(),
+                surface: surface.copied(),
+                gravity: gravity.copied(),
+            },
+        )
+        .collect();
+
+    let contacts = simulate(&mut bodies, dimensions, time.delta_seconds());
+
+    for body in bodies {
+        let (_, mut transform, mut collider, velocity, ..) =
+            colliders.get_mut(body.entity).unwrap();
+        collider.flags = body.collider.flags;
+        if let (Some(mut velocity), Some(stepped)) = (velocity, body.velocity) {
+            transform.translation = body.position;
+            *velocity = stepped;
+        }
+    }
+
+    for contact in contacts {
+        match contact.kind {
+            ColliderKind::Death if players.get(contact.entity).is_ok() => {
+                events.send(GameEvent::Death)
+            }
+            ColliderKind::Win => events.send(GameEvent::Win),
+            _ => {}
+        }
+        contact_events.send(contact);
+    }
+}
+
+/// Size of the world in units, or a default size while the map is loading.
+pub fn map_dimensions(map: Option<&TiledMap>) -> (f32, f32) {
+    map.map(|tm| {
+        (
+            tm.map.width as f32 * BLOCK_SIZE,
+            tm.map.height as f32 * BLOCK_SIZE,
+        )
+    })
+    .unwrap_or((16.0 * BLOCK_SIZE, 16.0 * BLOCK_SIZE))
+}
+
+/// How many sub-steps it takes for no body to move further than [`MAX_STEP`]
+/// in one of them, when the fastest moves `furthest` over the whole step.
+fn sub_steps(furthest: f32) -> usize {
+    ((furthest / MAX_STEP).ceil() as usize).clamp(1, MAX_STEPS)
+}
+
+/// Advances `bodies` by `delta` seconds. The step is split into enough
+/// sub-steps that no body moves further than [`MAX_STEP`] in one of them, so
+/// contacts are never missed at low frame rates, up to [`MAX_STEPS`] of them.
+pub fn simulate(bodies: &mut [Body], dimensions: (f32, f32), delta: f32) -> Vec<Contact> {
+    // impulses and forces land before anything moves, the same way gravity
+    // always has
+    for velocity in bodies.iter_mut().filter_map(|body| body.velocity.as_mut()) {
+        velocity.linvel += velocity.impulse + velocity.force * delta;
+        velocity.impulse = Vec3::ZERO;
+        velocity.force = Vec3::ZERO;
+    }
+
+    let furthest = bodies
+        .iter()
+        .filter_map(|body| body.velocity.as_ref())
+        .map(|velocity| velocity.linvel.abs().max_element() * delta)
+        .fold(0.0, f32::max);
+    let steps = sub_steps(furthest);
+
     let mut partition = SpatialPartition::new(dimensions.0 as usize, dimensions.1 as usize);
-    let solid: Vec<(Entity, &Transform, &Collider)> = colliders
+    partition.fill(
+        bodies
+            .iter()
+            .filter(|body| body.collider.weight() >= 1000.0),
+    );
+
+    for body in bodies.iter_mut() {
+        body.collider.flags = CollisionFlags::empty();
+    }
+
+    let start: Vec<Vec3> = bodies.iter().map(|body| body.position).collect();
+    let incoming: HashMap<Entity, Vec3> = bodies
         .iter()
-        .filter(|(_, _, c)| c.weight() >= 1000.0)
+        .filter_map(|body| Some((body.entity, body.velocity.as_ref()?.linvel)))
         .collect();
-    partition.fill(&solid);
-    let movables: Vec<(Entity, Collider)> = colliders
+    let mut remainders = vec![Vec2::ZERO; bodies.len()];
+    let mut contacts = Vec::new();
+    for _ in 0..steps {
+        step(
+            bodies,
+            &partition,
+            delta / steps as f32,
+            &mut remainders,
+            &mut contacts,
+        );
+    }
+
+    // bodies resting on a surface take its friction and get carried along by it
+    let surfaces: HashMap<Entity, Surface> = bodies
         .iter()
-        .filter_map(|(e, _, c)| {
-            if c.weight() < 1000.0 {
-                Some((e, *c))
-            } else {
-                None
-            }
+        .filter_map(|body| body.surface.map(|surface| (body.entity, surface)))
+        .collect();
+    let floors: HashMap<Entity, CollisionFlags> = bodies
+        .iter()
+        .map(|body| (body.entity, body.gravity.unwrap_or_default().floor()))
+        .collect();
+    let footing: HashMap<Entity, Surface> = contacts
+        .iter()
+        .filter(|contact| {
+            floors
+                .get(&contact.entity)
+                .is_some_and(|&floor| contact.side.contains(floor))
+        })
+        .filter_map(|contact| {
+            surfaces
+                .get(&contact.other)
+                .map(|surface| (contact.entity, *surface))
         })
         .collect();
-    let delta = time.delta_seconds();
 
-    for (_, _, mut c) in colliders.iter_mut() {
-        c.flags = CollisionFlags::empty();
+    for (body, start) in bodies.iter_mut().zip(start) {
+        if let Some(velocity) = &mut body.velocity {
+            if delta > 0.0 {
+                velocity.linvel.x = (body.position.x - start.x) / delta;
+                velocity.linvel.y = (body.position.y - start.y) / delta;
+            }
+            // drag works along the floor and along gravity, and never takes
+            // off more than the whole speed however rough the floor or long
+            // the frame
+            let right = body.gravity.unwrap_or_default().right();
+            let down = Vec3::new(right.y, right.x, 0.0);
+            let surface = footing.get(&body.entity).copied().unwrap_or_default();
+            let along = velocity.linvel.dot(right) - surface.velocity;
+            let slowdown = (velocity.drag.dot(right) * surface.friction * delta).min(1.0);
+            velocity.linvel -= right * along * slowdown;
+            let fall = velocity.linvel.dot(down);
+            velocity.linvel -= down * fall * (velocity.drag.dot(down) * delta).min(1.0);
+        }
+    }
+
+    // the same pair usually touches in several passes, only report it once
+    let mut seen: HashMap<(Entity, Entity), usize> = HashMap::new();
+    let mut unique: Vec<Contact> = Vec::new();
+    for contact in contacts {
+        match seen.get(&(contact.entity, contact.other)) {
+            Some(&i) => unique[i].side |= contact.side,
+            None => {
+                seen.insert((contact.entity, contact.other), unique.len());
+                unique.push(Contact {
+                    velocity: incoming.get(&contact.entity).copied().unwrap_or_default(),
+                    ..contact
+                });
+            }
+        }
     }
+    unique
+}
+
+/// Moves every body by one sub-step and resolves the overlaps, first
+/// vertically and then horizontally. Positions stay on whole pixels, the
+/// fraction of a pixel left over is carried to the next sub-step in
+/// `remainders` unless the body got stopped.
+fn step(
+    bodies: &mut [Body],
+    partition: &SpatialPartition,
+    delta: f32,
+    remainders: &mut [Vec2],
+    contacts: &mut Vec<Contact>,
+) {
+    let colliders: HashMap<Entity, Collider> = bodies
+        .iter()
+        .map(|body| (body.entity, body.collider))
+        .collect();
+    let movables: Vec<(Entity, Collider)> = bodies
+        .iter()
+        .filter(|body| body.collider.weight() < 1000.0)
+        .map(|body| (body.entity, body.collider))
+        .collect();
 
+    let mut targets: Vec<Vec3> = Vec::with_capacity(bodies.len());
     let mut positions: HashMap<Entity, (Vec3, CollisionFlags)> = HashMap::new();
-    for (entity, transform, collider) in colliders.iter() {
-        let mut position = transform.translation;
-        if let Ok(velocity) = velocity_query.get_component::<Velocity>(entity) {
-            position.y = (position.y + velocity.linvel.y * delta).floor();
+    for (body, remainder) in bodies.iter().zip(remainders.iter_mut()) {
+        let mut position = body.position;
+        if let Some(velocity) = &body.velocity {
+            let distance = velocity.linvel.y * delta + remainder.y;
+            remainder.y = distance - distance.floor();
+            position.y += distance.floor();
         }
-        if collider.weight() == f32::INFINITY {
-            positions.insert(entity, (position, CollisionFlags::all()));
+        targets.push(position);
+        if body.collider.weight() == f32::INFINITY {
+            positions.insert(body.entity, (position, CollisionFlags::all()));
         } else {
-            positions.insert(entity, (position, CollisionFlags::empty()));
+            positions.insert(body.entity, (position, CollisionFlags::empty()));
         }
     }
-    let mut update_velocity = HashSet::new();
-    let mut update: HashSet<Entity> = movables.clone().into_iter().map(|(e, _)| e).collect();
 
-    while !update.is_empty() {
-        let mut again: HashSet<Entity> = HashSet::new();
-        for entity in update {
-            let collider = colliders.get_component::<Collider>(entity).unwrap();
-            let state = *positions.get(&entity).unwrap();
-            let mut position = state.0;
-            let mut flags = state.1;
-            let size = collider.size;
-            for (other_entity, other_collider) in partition
-                .possibilities(position, collider.size)
-                .iter()
-                .map(|(e, _, c)| (*e, *c))
-                .chain(movables.iter().filter_map(|(e, c)| {
-                    if e.id() != entity.id() {
-                        Some((*e, *c))
-                    } else {
-                        None
-                    }
-                }))
-            {
-                let other_size = other_collider.size;
-                let other_state = *positions.get(&other_entity).unwrap();
-                let mut other_position = other_state.0;
-                let other_flags = other_state.1;
-                if let Some(collision) = collide(other_position, other_size, position, size) {
-                    if matches!(collision, Collision::Top)
-                        | matches!(collision, Collision::Bottom)
-                        | matches!(collision, Collision::Inside)
-                    {
-                        match other_collider.kind {
-                            ColliderKind::Movable(other_weight) => {
-                                let push = push_force(
-                                    &collision,
-                                    position,
-                                    size,
-                                    other_position,
-                                    other_size,
-                                );
-                                let f = CollisionFlags::from(collision);
-                                let of = f.opposite();
-                                if collider.weight() < other_weight {
-                                    if flags & of == CollisionFlags::empty() {
-                                        flags |= f;
-                                        position += push;
-                                        again.insert(entity);
-                                        update_velocity.insert(entity);
-                                        if !other_flags.is_locked(of) {
-                                            positions.insert(
-                                                other_entity,
-                                                (other_position, other_flags & (!of)),
-                                            );
-                                        }
-                                        if other_flags.is_locked(f) {
-                                            flags |= f.to_lock();
-                                        }
-                                    } else if flags.is_locked(of) {
-                                        other_position -= push;
-                                        again.insert(other_entity);
-                                        update_velocity.insert(other_entity);
-                                        positions.insert(
-                                            other_entity,
-                                            (other_position, other_flags | of),
-                                        );
-                                    } else {
-                                        flags |= f;
-                                        position += push;
-                                        again.insert(entity);
-                                        update_velocity.insert(entity);
-                                        if !other_flags.is_locked(of) {
-                                            positions.insert(
-                                                other_entity,
-                                                (other_position, other_flags & (!of)),
-                                            );
-                                        }
-                                        if other_flags.is_locked(f) {
-                                            flags |= f.to_lock();
-                                        }
-                                    }
-                                } else if other_flags & f == CollisionFlags::empty() {
-                                    other_position -= push;
-                                    again.insert(other_entity);
-                                    update_velocity.insert(other_entity);
-                                    positions
-                                        .insert(other_entity, (other_position, other_flags | of));
-                                } else if other_flags.is_locked(f) {
-                                    flags |= f;
-                                    position += push;
-                                    again.insert(entity);
-                                    update_velocity.insert(entity);
-                                    if !other_flags.is_locked(of) {
-                                        positions.insert(
-                                            other_entity,
-                                            (other_position, other_flags & (!of)),
-                                        );
-                                    }
-                                    if other_flags.is_locked(f) {
-                                        flags |= f.to_lock();
-                                    }
-                                } else {
-                                    other_position -= push;
-                                    again.insert(other_entity);
-                                    update_velocity.insert(other_entity);
-                                    positions
-                                        .insert(other_entity, (other_position, other_flags | of));
-                                }
-                            }
-                            ColliderKind::Death => {
-                                if entity == player_entity {
-                                    events.send(GameEvent::Death);
-                                }
-                            }
-                            ColliderKind::Sensor => {
-                                positions.insert(
-                                    other_entity,
-                                    (other_position, other_flags | CollisionFlags::TOP),
-                                );
-                            }
-                            ColliderKind::Win => {
-                                events.send(GameEvent::Win);
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+    resolve(
+        Axis::Y,
+        &colliders,
+        &movables,
+        partition,
+        &mut positions,
+        contacts,
+    );
+
+    for (i, body) in bodies.iter_mut().enumerate() {
+        let (position, flags) = positions[&body.entity];
+        if body.velocity.is_some() {
+            if position.y != targets[i].y {
+                remainders[i].y = 0.0;
             }
-            positions.insert(entity, (position, flags));
+            body.position.y = position.y;
         }
-        update = again;
+        body.collider.flags |= flags;
     }
 
-    for (entity, (position, flags)) in &positions {
-        let entity = *entity;
-        if delta > 0.0 {
-            if let Ok(mut velocity) = velocity_query.get_component_mut::<Velocity>(entity) {
-                let target = position.y;
-                let current = colliders
-                    .get_component::<Transform>(entity)
-                    .unwrap()
-                    .translation
-                    .y;
-                velocity.linvel.y = (target - current) / delta;
-            }
+    targets.clear();
+    for (body, remainder) in bodies.iter().zip(remainders.iter_mut()) {
+        let flags = positions[&body.entity].1;
+        let mut position = body.position;
+        if let Some(velocity) = &body.velocity {
+            let distance = velocity.linvel.x * delta + remainder.x;
+            remainder.x = distance - distance.trunc();
+            position.x += distance.trunc();
         }
-        colliders
-            .get_component_mut::<Collider>(entity)
-            .unwrap()
-            .flags |= *flags;
+        targets.push(position);
+        positions.insert(body.entity, (position, flags));
     }
 
-    for (entity, transform, _) in colliders.iter() {
-        let mut position = transform.translation;
-        if let Ok(velocity) = velocity_query.get_component::<Velocity>(entity) {
-            if velocity.linvel.x >= 0.0 {
-                position.x = (position.x + velocity.linvel.x * delta).floor();
-            } else {
-                position.x = (position.x + velocity.linvel.x * delta).ceil();
+    resolve(
+        Axis::X,
+        &colliders,
+        &movables,
+        partition,
+        &mut positions,
+        contacts,
+    );
+
+    for (i, body) in bodies.iter_mut().enumerate() {
+        let (position, flags) = positions[&body.entity];
+        if body.velocity.is_some() {
+            if position.x != targets[i].x {
+                remainders[i].x = 0.0;
             }
+            body.position.x = position.x;
         }
-        let flags = positions.get(&entity).unwrap().1;
-        positions.insert(entity, (position, flags));
+        body.collider.flags |= flags;
     }
-    let mut update: HashSet<Entity> = movables.clone().into_iter().map(|(e, _)| e).collect();
+}
+
+/// Pushes overlapping movables apart along `axis` until nothing moves.
+fn resolve(
+    axis: Axis,
+    colliders: &HashMap<Entity, Collider>,
+    movables: &[(Entity, Collider)],
+    partition: &SpatialPartition,
+    positions: &mut HashMap<Entity, (Vec3, CollisionFlags)>,
+    contacts: &mut Vec<Contact>,
+) {
+    let mut update: HashSet<Entity> = movables.iter().map(|(e, _)| *e).collect();
 
     while !update.is_empty() {
         let mut again: HashSet<Entity> = HashSet::new();
         for entity in update {
-            let collider = colliders.get_component::<Collider>(entity).unwrap();
-            let state = *positions.get(&entity).unwrap();
+            let collider = colliders[&entity];
+            let state = positions[&entity];
             let mut position = state.0;
             let mut flags = state.1;
             let size = collider.size;
@@ -342,141 +464,98 @@ fn handle_collisions(
                 }))
             {
                 let other_size = other_collider.size;
-                let other_state = *positions.get(&other_entity).unwrap();
+                let other_state = positions[&other_entity];
                 let mut other_position = other_state.0;
                 let other_flags = other_state.1;
-                if let Some(collision) = collide(other_position, other_size, position, size) {
-                    if matches!(collision, Collision::Right)
-                        | matches!(collision, Collision::Left)
-                        | matches!(collision, Collision::Inside)
+                let collision = match collide(other_position, other_size, position, size) {
+                    Some(collision)
+                        if Axis::from(&collision) == axis
+                            || matches!(collision, Collision::Inside) =>
                     {
-                        match other_collider.kind {
-                            ColliderKind::Movable(other_weight) => {
-                                let push = push_force(
-                                    &collision,
-                                    position,
-                                    size,
-                                    other_position,
-                                    other_size,
-                                );
-                                let f = CollisionFlags::from(collision);
-                                let of = f.opposite();
-                                if collider.weight() < other_weight {
-                                    if flags & of == CollisionFlags::empty() {
-                                        flags |= f;
-                                        position += push;
-                                        again.insert(entity);
-                                        update_velocity.insert(entity);
-                                        if !other_flags.is_locked(of) {
-                                            positions.insert(
-                                                other_entity,
-                                                (other_position, other_flags & (!of)),
-                                            );
-                                        }
-                                        if other_flags.is_locked(f) {
-                                            flags |= f.to_lock();
-                                        }
-                                    } else if flags.is_locked(of) {
-                                        other_position -= push;
-                                        again.insert(other_entity);
-                                        update_velocity.insert(other_entity);
-                                        positions.insert(
-                                            other_entity,
-                                            (other_position, other_flags | of),
-                                        );
-                                    } else {
-                                        flags |= f;
-                                        position += push;
-                                        again.insert(entity);
-                                        update_velocity.insert(entity);
-                                        if !other_flags.is_locked(of) {
-                                            positions.insert(
-                                                other_entity,
-                                                (other_position, other_flags & (!of)),
-                                            );
-                                        }
-                                        if other_flags.is_locked(f) {
-                                            flags |= f.to_lock();
-                                        }
-                                    }
-                                } else if other_flags & f == CollisionFlags::empty() {
-                                    other_position -= push;
-                                    again.insert(other_entity);
-                                    update_velocity.insert(other_entity);
-                                    positions
-                                        .insert(other_entity, (other_position, other_flags | of));
-                                } else if other_flags.is_locked(f) {
-                                    flags |= f;
-                                    position += push;
-                                    again.insert(entity);
-                                    update_velocity.insert(entity);
-                                    if !other_flags.is_locked(of) {
-                                        positions.insert(
-                                            other_entity,
-                                            (other_position, other_flags & (!of)),
-                                        );
-                                    }
-                                    if other_flags.is_locked(f) {
-                                        flags |= f.to_lock();
-                                    }
-                                } else {
-                                    other_position -= push;
-                                    again.insert(other_entity);
-                                    update_velocity.insert(other_entity);
-                                    positions
-                                        .insert(other_entity, (other_position, other_flags | of));
+                        collision
+                    }
+                    _ => continue,
+                };
+                let push = push_force(&collision, position, size, other_position, other_size);
+                let f = CollisionFlags::from(collision);
+                if !matches!(other_collider.kind, ColliderKind::None) {
+                    contacts.push(Contact {
+                        entity,
+                        other: other_entity,
+                        side: f,
+                        kind: other_collider.kind,
+                        velocity: Vec3::ZERO,
+                    });
+                }
+                match other_collider.kind {
+                    ColliderKind::Movable(other_weight) => {
+                        let of = f.opposite();
+                        if collider.weight() < other_weight {
+                            if flags & of == CollisionFlags::empty() {
+                                flags |= f;
+                                position += push;
+                                again.insert(entity);
+                                if !other_flags.is_locked(of) {
+                                    positions.insert(
+                                        other_entity,
+                                        (other_position, other_flags & (!of)),
+                                    );
                                 }
-                            }
-                            ColliderKind::Death => {
-                                if entity == player_entity {
-                                    events.send(GameEvent::Death);
+                                if other_flags.is_locked(f) {
+                                    flags |= f.to_lock();
+                                }
+                            } else if flags.is_locked(of) {
+                                other_position -= push;
+                                again.insert(other_entity);
+                                positions.insert(other_entity, (other_position, other_flags | of));
+                            } else {
+                                flags |= f;
+                                position += push;
+                                again.insert(entity);
+                                if !other_flags.is_locked(of) {
+                                    positions.insert(
+                                        other_entity,
+                                        (other_position, other_flags & (!of)),
+                                    );
+                                }
+                                if other_flags.is_locked(f) {
+                                    flags |= f.to_lock();
                                 }
                             }
-                            ColliderKind::Sensor => {
-                                positions.insert(
-                                    other_entity,
-                                    (other_position, other_flags | CollisionFlags::TOP),
-                                );
+                        } else if other_flags & f == CollisionFlags::empty() {
+                            other_position -= push;
+                            again.insert(other_entity);
+                            positions.insert(other_entity, (other_position, other_flags | of));
+                        } else if other_flags.is_locked(f) {
+                            flags |= f;
+                            position += push;
+                            again.insert(entity);
+                            if !other_flags.is_locked(of) {
+                                positions
+                                    .insert(other_entity, (other_position, other_flags & (!of)));
                             }
-                            ColliderKind::Win => {
-                                events.send(GameEvent::Win);
+                            if other_flags.is_locked(f) {
+                                flags |= f.to_lock();
                             }
-                            _ => {}
+                        } else {
+                            other_position -= push;
+                            again.insert(other_entity);
+                            positions.insert(other_entity, (other_position, other_flags | of));
                         }
                     }
+                    ColliderKind::Sensor => {
+                        positions.insert(
+                            other_entity,
+                            (other_position, other_flags | CollisionFlags::TOP),
+                        );
+                    }
+                    _ => {}
                 }
             }
             positions.insert(entity, (position, flags));
         }
         update = again;
     }
-
-    for (entity, (position, flags)) in positions {
-        if delta > 0.0 {
-            if let Ok(mut velocity) = velocity_query.get_component_mut::<Velocity>(entity) {
-                let target = position.x;
-                let current = colliders
-                    .get_component::<Transform>(entity)
-                    .unwrap()
-                    .translation
-                    .x;
-                velocity.linvel.x = (target - current) / delta;
-            }
-        }
-        colliders
-            .get_component_mut::<Collider>(entity)
-            .unwrap()
-            .flags |= flags;
-    }
-
-    for (entity, mut transform, _) in colliders.iter_mut() {
-        if let Ok(mut velocity) = velocity_query.get_component_mut::<Velocity>(entity) {
-            let drag = velocity.drag;
-            transform.translation = (transform.translation + velocity.linvel * delta).floor();
-            velocity.linvel.x -= velocity.linvel.x * drag.x * delta;
-            velocity.linvel.y -= velocity.linvel.y * drag.y * delta;
-        }
-    }
 }
 
 bitflags! {
@@ -499,6 +578,21 @@ impl Default for CollisionFlags {
 }
 
 impl CollisionFlags {
+    /// The side of a collider facing `direction`, snapped to an axis.
+    pub fn facing(direction: Vec3) -> Self {
+        if direction.x.abs() > direction.y.abs() {
+            if direction.x > 0.0 {
+                CollisionFlags::RIGHT
+            } else {
+                CollisionFlags::LEFT
+            }
+        } else if direction.y > 0.0 {
+            CollisionFlags::TOP
+        } else {
+            CollisionFlags::BOTTOM
+        }
+    }
+
     fn is_locked(&self, check: Self) -> bool {
         let bits = check.bits();
         assert_eq!((bits & 0x0F).count_ones(), 1);
@@ -556,13 +650,13 @@ impl SpatialPartition {
     // | b | d |
     // ---------
 
-    fn fill(&mut self, entities: &[(Entity, &Transform, &Collider)]) {
-        for (entity, transform, collider) in entities {
-            let position = transform.translation;
-            let (min_x, max_x, min_y, max_y) = self.spatial_index(position, collider.size);
+    fn fill<'a>(&mut self, bodies: impl IntoIterator<Item = &'a Body>) {
+        for body in bodies {
+            let (min_x, max_x, min_y, max_y) =
+                self.spatial_index(body.position, body.collider.size);
             for i in min_x..=max_x {
                 for j in min_y..=max_y {
-                    self.partition[i][j].push((*entity, transform.translation, **collider));
+                    self.partition[i][j].push((body.entity, body.position, body.collider));
                 }
             }
         }
@@ -608,3 +702,112 @@ fn push_force(collision: &Collision, a_pos: Vec3, a_size: Vec2, b_pos: Vec3, b_s
     .extend(0.0))
     .round()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(id: u32, position: Vec3, size: Vec2, kind: ColliderKind) -> Body {
+        Body {
+            entity: Entity::from_raw(id),
+            position,
+            collider: Collider {
+                size,
+                kind,
+                flags: CollisionFlags::empty(),
+            },
+            velocity: None,
+            surface: None,
+            gravity: None,
+        }
+    }
+
+    #[test]
+    fn falling_box_hits_spikes_at_low_frame_rate() {
+        let floor_top = 4.0 * BLOCK_SIZE;
+        let strip = Vec2::new(BLOCK_SIZE / 1.25, BLOCK_SIZE / 5.0);
+        let mut falling = body(
+            0,
+            Vec3::new(5.0 * BLOCK_SIZE, floor_top + 2.0 * BLOCK_SIZE, 0.0),
+            Vec2::splat(BLOCK_SIZE / 2.0),
+            ColliderKind::Movable(5.0),
+        );
+        falling.velocity = Some(Velocity {
+            linvel: Vec3::new(0.0, -3000.0, 0.0),
+            drag: Vec3::ZERO,
+            ..default()
+        });
+        let mut bodies = vec![
+            falling,
+            body(
+                1,
+                Vec3::new(5.0 * BLOCK_SIZE, floor_top + strip.y / 2.0, 0.0),
+                strip,
+                ColliderKind::Death,
+            ),
+            body(
+                2,
+                Vec3::new(5.0 * BLOCK_SIZE, floor_top - BLOCK_SIZE / 2.0, 0.0),
+                Vec2::new(3.0 * BLOCK_SIZE, BLOCK_SIZE),
+                ColliderKind::Movable(f32::INFINITY),
+            ),
+        ];
+
+        // 300 pixels in a single 10 fps frame, far more than the strip is tall
+        let contacts = simulate(&mut bodies, (16.0 * BLOCK_SIZE, 16.0 * BLOCK_SIZE), 0.1);
+
+        assert!(contacts.iter().any(|contact| {
+            contact.entity == Entity::from_raw(0)
+                && contact.other == Entity::from_raw(1)
+                && matches!(contact.kind, ColliderKind::Death)
+        }));
+        let bottom = bodies[0].position.y - BLOCK_SIZE / 4.0;
+        assert!(
+            bottom >= floor_top && bottom < floor_top + strip.y,
+            "box fell to {} instead of stopping on the floor under the strip",
+            bottom
+        );
+    }
+
+    #[test]
+    fn runaway_velocity_takes_a_bounded_number_of_sub_steps() {
+        assert_eq!(sub_steps(0.0), 1);
+        assert_eq!(sub_steps(MAX_STEP * 2.5), 3);
+        assert_eq!(sub_steps(f32::MAX), MAX_STEPS);
+        assert_eq!(sub_steps(f32::INFINITY), MAX_STEPS);
+    }
+
+    #[test]
+    fn sideways_conveyor_carries_bodies_along_the_wall() {
+        let mut wall = body(
+            1,
+            Vec3::new(2.0 * BLOCK_SIZE, 4.0 * BLOCK_SIZE, 0.0),
+            Vec2::splat(BLOCK_SIZE),
+            ColliderKind::Movable(f32::INFINITY),
+        );
+        // a rubbery conveyor, rough enough to stop anything in one frame
+        wall.surface = Some(Surface {
+            friction: 50.0,
+            velocity: 200.0,
+        });
+        let mut resting = body(
+            0,
+            Vec3::new(2.5 * BLOCK_SIZE + BLOCK_SIZE / 4.0, 4.0 * BLOCK_SIZE, 0.0),
+            Vec2::splat(BLOCK_SIZE / 2.0),
+            ColliderKind::Movable(1.0),
+        );
+        resting.gravity = Some(Gravity(Vec3::new(-6000.0, 0.0, 0.0)));
+        resting.velocity = Some(Velocity {
+            linvel: Vec3::new(-600.0, -100.0, 0.0),
+            drag: Vec3::splat(10.0),
+            ..default()
+        });
+        let mut bodies = vec![resting, wall];
+
+        simulate(&mut bodies, (16.0 * BLOCK_SIZE, 16.0 * BLOCK_SIZE), 0.1);
+
+        let linvel = bodies[0].velocity.as_ref().unwrap().linvel;
+        assert_eq!(linvel.y, 200.0);
+        assert_eq!(linvel.x, 0.0);
+    }
+}
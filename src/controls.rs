@@ -0,0 +1,408 @@
+use std::{collections::HashMap, fs};
+
+use bevy::{app::AppExit, ecs::schedule::StateError, input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{state::GameState, ui::UiButton};
+
+/// File the bindings are read from at startup and written to after rebinding.
+const CONFIG_PATH: &str = "controls.ron";
+/// How far a stick has to be pushed for its binding to count as pressed.
+const AXIS_THRESHOLD: f32 = 0.5;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActionMap::load())
+            .init_resource::<Input<Action>>()
+            .init_resource::<Rebinding>()
+            .add_system_to_stage(CoreStage::PreUpdate, update_actions.after(InputSystem))
+            .add_system(toggle_pause.before(rebind))
+            .add_system(rebind)
+            .add_system_set(SystemSet::on_enter(GameState::Pause).with_system(build_rebind_buttons))
+            .add_system_set(
+                SystemSet::on_update(GameState::Pause).with_system(rebind_button_interaction),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Pause).with_system(destroy_rebind_buttons),
+            );
+    }
+}
+
+/// Something the player can do, independent of the key or button doing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Left,
+    Right,
+    /// Jumps, climbs or flies up.
+    Jump,
+    /// Climbs or flies down.
+    Down,
+    Interact,
+    /// Takes over a nearby enemy, or lets go of it.
+    Hack,
+    SwitchRobot,
+    Restart,
+    /// Pauses the game, or quits from the main menu.
+    Pause,
+}
+
+/// Actions in the order the settings screen lists them.
+const ACTIONS: [Action; 9] = [
+    Action::Left,
+    Action::Right,
+    Action::Jump,
+    Action::Down,
+    Action::Interact,
+    Action::Hack,
+    Action::SwitchRobot,
+    Action::Restart,
+    Action::Pause,
+];
+
+/// A key, gamepad button or direction of a gamepad stick.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    /// The axis pushed past the threshold toward the sign of the value.
+    Axis(GamepadAxisType, f32),
+}
+
+impl Binding {
+    fn is_pressed(
+        &self,
+        keys: &Input<KeyCode>,
+        gamepads: &Gamepads,
+        buttons: &Input<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+    ) -> bool {
+        match *self {
+            Binding::Key(key) => keys.pressed(key),
+            Binding::Button(button) => gamepads
+                .iter()
+                .any(|&gamepad| buttons.pressed(GamepadButton(gamepad, button))),
+            Binding::Axis(axis, sign) => gamepads.iter().any(|&gamepad| {
+                matches!(
+                    axes.get(GamepadAxis(gamepad, axis)),
+                    Some(value) if value * sign.signum() > AXIS_THRESHOLD
+                )
+            }),
+        }
+    }
+
+    fn is_gamepad(&self) -> bool {
+        !matches!(self, Binding::Key(_))
+    }
+
+    /// Whether both are keys, both gamepad buttons or both stick directions.
+    fn is_same_kind(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Button(button) => format!("{:?}", button),
+            Binding::Axis(axis, sign) => {
+                format!("{:?}{}", axis, if *sign < 0.0 { "-" } else { "+" })
+            }
+        }
+    }
+}
+
+/// The keys and buttons bound to every action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionMap(pub HashMap<Action, Vec<Binding>>);
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        use Binding::*;
+        use GamepadAxisType::*;
+        use GamepadButtonType::*;
+        ActionMap(HashMap::from([
+            (
+                Action::Left,
+                vec![
+                    Key(KeyCode::A),
+                    Key(KeyCode::Left),
+                    Button(DPadLeft),
+                    Axis(LeftStickX, -1.0),
+                ],
+            ),
+            (
+                Action::Right,
+                vec![
+                    Key(KeyCode::D),
+                    Key(KeyCode::Right),
+                    Button(DPadRight),
+                    Axis(LeftStickX, 1.0),
+                ],
+            ),
+            (
+                Action::Jump,
+                vec![
+                    Key(KeyCode::W),
+                    Key(KeyCode::Up),
+                    Button(South),
+                    Button(DPadUp),
+                ],
+            ),
+            (
+                Action::Down,
+                vec![
+                    Key(KeyCode::S),
+                    Key(KeyCode::Down),
+                    Button(DPadDown),
+                    Axis(LeftStickY, -1.0),
+                ],
+            ),
+            (Action::Interact, vec![Key(KeyCode::E), Button(West)]),
+            (Action::Hack, vec![Key(KeyCode::Q), Button(North)]),
+            (
+                Action::SwitchRobot,
+                vec![Key(KeyCode::Tab), Button(RightTrigger)],
+            ),
+            (Action::Restart, vec![Key(KeyCode::R), Button(Select)]),
+            (Action::Pause, vec![Key(KeyCode::Escape), Button(Start)]),
+        ]))
+    }
+}
+
+impl ActionMap {
+    /// Reads the bindings from the config file, falling back to the defaults
+    /// when there is none or it can't be read.
+    fn load() -> Self {
+        let config = match fs::read_to_string(CONFIG_PATH) {
+            Ok(config) => config,
+            Err(_) => return ActionMap::default(),
+        };
+        match ron::from_str::<ActionMap>(&config) {
+            Ok(mut map) => {
+                // actions added since the file was written keep their defaults
+                for (action, bindings) in ActionMap::default().0 {
+                    map.0.entry(action).or_insert(bindings);
+                }
+                map
+            }
+            Err(err) => {
+                warn!("Ignoring {}: {}", CONFIG_PATH, err);
+                ActionMap::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let config = match ron::ser::to_string_pretty(self, default()) {
+            Ok(config) => config,
+            Err(err) => return warn!("Could not save {}: {}", CONFIG_PATH, err),
+        };
+        if let Err(err) = fs::write(CONFIG_PATH, config) {
+            warn!("Could not save {}: {}", CONFIG_PATH, err);
+        }
+    }
+
+    /// Name of the first key bound to the action, or of whatever it is bound
+    /// to when there is no key, for telling the player what to press.
+    pub fn key(&self, action: Action) -> String {
+        let bindings = &self.0[&action];
+        bindings
+            .iter()
+            .find(|binding| !binding.is_gamepad())
+            .or_else(|| bindings.first())
+            .map_or_else(|| "nothing".to_string(), Binding::name)
+    }
+
+    fn label(&self, action: Action) -> String {
+        let bindings: Vec<String> = self.0[&action].iter().map(Binding::name).collect();
+        format!("{:?}: {}", action, bindings.join(" "))
+    }
+}
+
+/// Action waiting for the next key or button press to be bound to it.
+#[derive(Default)]
+struct Rebinding(Option<Action>);
+
+/// Settings screen button that rebinds one action.
+#[derive(Component, Clone, Copy)]
+struct RebindButton(Action);
+
+fn update_actions(
+    map: Res<ActionMap>,
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut actions: ResMut<Input<Action>>,
+) {
+    actions.clear();
+    for (&action, bindings) in map.0.iter() {
+        if bindings
+            .iter()
+            .any(|binding| binding.is_pressed(&keys, &gamepads, &buttons, &axes))
+        {
+            actions.press(action);
+        } else {
+            actions.release(action);
+        }
+    }
+}
+
+fn toggle_pause(
+    actions: Res<Input<Action>>,
+    rebinding: Res<Rebinding>,
+    mut state: ResMut<State<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    // the key being bound may well be the pause key
+    if !actions.just_pressed(Action::Pause) || rebinding.0.is_some() {
+        return;
+    }
+    let result = match state.current() {
+        GameState::MainMenu => return exit.send(AppExit),
+        GameState::Play => state.push(GameState::Pause),
+        GameState::Pause => state.pop(),
+    };
+    // the pause button may have queued the change already this frame
+    if let Err(err) = result {
+        if !matches!(err, StateError::StateAlreadyQueued) {
+            warn!("Could not toggle pause: {:?}", err);
+        }
+    }
+}
+
+fn build_rebind_buttons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map: Res<ActionMap>,
+) {
+    for (i, action) in ACTIONS.into_iter().enumerate() {
+        commands
+            .spawn_bundle(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(600.0), Val::Px(40.0)),
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(15.0 + 50.0 * i as f32),
+                        right: Val::Px(15.0),
+                        ..default()
+                    },
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(UiButton)
+            .insert(RebindButton(action))
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        map.label(action),
+                        TextStyle {
+                            font: asset_server.load("VT323-Regular.ttf"),
+                            font_size: 30.0,
+                            color: Color::BLACK,
+                        },
+                        default(),
+                    ),
+                    ..default()
+                });
+            });
+    }
+}
+
+type RebindInteraction<'a> = (&'a Interaction, &'a RebindButton, &'a Children);
+
+fn rebind_button_interaction(
+    mut rebinding: ResMut<Rebinding>,
+    interaction_query: Query<RebindInteraction, Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, RebindButton(action), children) in interaction_query.iter() {
+        if interaction == &Interaction::Clicked {
+            rebinding.0 = Some(*action);
+            for &child in children.iter() {
+                if let Ok(mut text) = text_query.get_mut(child) {
+                    text.sections[0].value = format!(
+                        "{:?}: press a key or button to replace the first one, Escape to cancel",
+                        action
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Binds the next key or gamepad button pressed to the action being rebound,
+/// or gives up on Escape. It replaces the first binding of the same kind, so
+/// rebinding a key only changes the key shown in hints and keeps the other
+/// keys, gamepad buttons and sticks. An action the binding is taken from gets
+/// the one it replaced instead.
+fn rebind(
+    mut rebinding: ResMut<Rebinding>,
+    mut map: ResMut<ActionMap>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    buttons_query: Query<(&RebindButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    let action = match rebinding.0 {
+        Some(action) => action,
+        None => return,
+    };
+    let binding = match keys.get_just_pressed().next() {
+        Some(KeyCode::Escape) => None,
+        Some(&key) => Some(Binding::Key(key)),
+        None => match buttons.get_just_pressed().next() {
+            Some(button) => Some(Binding::Button(button.1)),
+            None => return,
+        },
+    };
+    rebinding.0 = None;
+    if let Some(binding) = binding {
+        let bindings = map.0.entry(action).or_default();
+        let slot = bindings
+            .iter()
+            .position(|bound| bound.is_same_kind(&binding));
+        let replaced = match slot {
+            _ if bindings.contains(&binding) => None,
+            Some(slot) => Some(std::mem::replace(&mut bindings[slot], binding)),
+            None => {
+                bindings.insert(0, binding);
+                None
+            }
+        };
+        for (_, bindings) in map.0.iter_mut().filter(|(other, _)| **other != action) {
+            if let Some(index) = bindings.iter().position(|bound| *bound == binding) {
+                match replaced {
+                    Some(replaced) if !bindings.contains(&replaced) => bindings[index] = replaced,
+                    _ => {
+                        bindings.remove(index);
+                    }
+                }
+            }
+        }
+        map.save();
+    }
+
+    for (RebindButton(button_action), children) in buttons_query.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(child) {
+                text.sections[0].value = map.label(*button_action);
+            }
+        }
+    }
+}
+
+fn destroy_rebind_buttons(
+    mut commands: Commands,
+    mut rebinding: ResMut<Rebinding>,
+    ui_components: Query<Entity, With<RebindButton>>,
+) {
+    rebinding.0 = None;
+    for entity in ui_components.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+
+use crate::{
+    animation::Animation,
+    carry::{Carried, Carryable},
+    collide::{Collider, ColliderKind, CollisionFlags, Contact},
+    inventory::Inventory,
+    map::BLOCK_SIZE,
+    network::LevelSignal,
+    player::{GameInput, Link, Player},
+    state::GameState,
+    tiled_loader::WorldObject,
+    velocity::{Gravity, Velocity},
+};
+
+/// Largest box the magnet of a drone can lift.
+const MAX_LOAD: f32 = BLOCK_SIZE / 2.0;
+
+pub struct DronePlugin;
+
+impl Plugin for DronePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Play).with_system(update_drone));
+    }
+}
+
+/// Flying robot that ignores gravity but runs on a battery.
+#[derive(Component, Debug)]
+pub struct Drone {
+    /// Seconds of flight left.
+    pub battery: f32,
+    /// Seconds of flight on a full battery.
+    pub capacity: f32,
+    /// Box hanging from the magnet.
+    pub load: Option<Entity>,
+}
+
+impl Drone {
+    /// Whether it still flies and takes inputs.
+    pub fn is_powered(&self) -> bool {
+        self.battery > 0.0
+    }
+}
+
+#[derive(Bundle)]
+pub struct DroneBundle {
+    #[bundle]
+    sprite_sheet: SpriteSheetBundle,
+    player: Player,
+    link: Link,
+    inventory: Inventory,
+    drone: Drone,
+    collider: Collider,
+    velocity: Velocity,
+    animation: Animation,
+    world_object: WorldObject,
+}
+
+impl DroneBundle {
+    pub fn new(transform: Transform, texture: Handle<TextureAtlas>, battery: f32) -> Self {
+        let size = Vec2::splat(0.6 * BLOCK_SIZE);
+        Self {
+            sprite_sheet: SpriteSheetBundle {
+                texture_atlas: texture,
+                sprite: TextureAtlasSprite {
+                    custom_size: Some(size),
+                    color: Color::rgb(0.6, 0.8, 1.0),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: transform.translation + Vec3::Z * 100.0,
+                    ..transform
+                },
+                ..default()
+            },
+            player: Player::default(),
+            link: Link::default(),
+            inventory: Inventory::default(),
+            drone: Drone {
+                battery,
+                capacity: battery,
+                load: None,
+            },
+            collider: Collider {
+                kind: ColliderKind::Movable(2.0),
+                size,
+                flags: CollisionFlags::empty(),
+            },
+            velocity: Velocity {
+                drag: Vec3::splat(10.0),
+                ..default()
+            },
+            animation: Animation {
+                timer: Timer::from_seconds(0.1, true),
+                running: false,
+            },
+            world_object: WorldObject,
+        }
+    }
+}
+
+/// Changes the velocity of a drone for one arrived input.
+pub fn apply_drone_input(input: &GameInput, velocity: &mut Velocity) {
+    let direction = match input {
+        GameInput::Left => -Vec3::X,
+        GameInput::Right => Vec3::X,
+        GameInput::Up => Vec3::Y,
+        GameInput::Down => -Vec3::Y,
+        GameInput::Jump | GameInput::Interact | GameInput::HoldJump => return,
+    };
+    velocity.linvel += direction * 100.0;
+}
+
+type DroneQuery<'a> = (
+    Entity,
+    &'a mut Link,
+    &'a mut Drone,
+    &'a Collider,
+    &'a mut Velocity,
+    &'a mut TextureAtlasSprite,
+    &'a mut Animation,
+);
+
+fn update_drone(
+    mut commands: Commands,
+    time: Res<Time>,
+    level_signal: Res<LevelSignal>,
+    mut contacts: EventReader<Contact>,
+    mut drones: Query<DroneQuery>,
+    boxes: Query<&Collider, (With<Carryable>, Without<Drone>)>,
+) {
+    let contacts: Vec<&Contact> = contacts.iter().collect();
+    for (entity, mut link, mut drone, collider, mut velocity, mut sprite, mut animation) in
+        drones.iter_mut()
+    {
+        // inputs sent before control moved on never arrive
+        if !drone.is_powered() {
+            link.drain();
+            continue;
+        }
+        drone.battery -= time.delta_seconds();
+        animation.running = true;
+        if !drone.is_powered() {
+            // out of power, drop whatever it holds and fall out of the sky,
+            // control moves on to the next robot
+            drone.battery = 0.0;
+            link.drain();
+            animation.running = false;
+            if let Some(load) = drone.load.take() {
+                commands.entity(load).remove::<Carried>();
+            }
+            commands.entity(entity).insert(Gravity::default());
+            continue;
+        }
+
+        if link.is_frozen(&level_signal) {
+            velocity.linvel = Vec3::ZERO;
+        }
+        for input in link.receive(time.delta_seconds(), &level_signal) {
+            match input {
+                GameInput::Left => sprite.flip_x = true,
+                GameInput::Right => sprite.flip_x = false,
+                // the magnet picks up a small box right under the drone or lets go
+                GameInput::Interact => match drone.load.take() {
+                    Some(load) => {
+                        commands.entity(load).remove::<Carried>();
+                    }
+                    None => {
+                        drone.load = contacts
+                            .iter()
+                            .filter_map(|contact| {
+                                if contact.entity == entity
+                                    && contact.side.contains(CollisionFlags::BOTTOM)
+                                {
+                                    Some(contact.other)
+                                } else if contact.other == entity
+                                    && contact.side.contains(CollisionFlags::TOP)
+                                {
+                                    Some(contact.entity)
+                                } else {
+                                    None
+                                }
+                            })
+                            .find_map(|other| {
+                                let size = boxes.get(other).ok()?.size;
+                                (size.max_element() <= MAX_LOAD).then_some((other, size))
+                            })
+                            .map(|(load, size)| {
+                                commands.entity(load).insert(Carried {
+                                    carrier: entity,
+                                    offset: -Vec3::Y * (collider.size.y + size.y) / 2.0,
+                                });
+                                load
+                            });
+                    }
+                },
+                _ => {}
+            }
+            apply_drone_input(&input, &mut velocity);
+        }
+    }
+}
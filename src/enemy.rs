@@ -0,0 +1,347 @@
+use bevy::prelude::*;
+
+use crate::{
+    collide::{Collider, ColliderKind, CollisionFlags, Contact, GameEvent},
+    hack::Hacked,
+    map::BLOCK_SIZE,
+    navigation::{self, Move, Navigation, Step},
+    path::PathFollower,
+    player::Player,
+    state::GameState,
+    tiled_loader::WorldObject,
+    velocity::{Gravity, Velocity},
+};
+
+/// How far ahead an enemy spots robots.
+const SIGHT: f32 = 4.0 * BLOCK_SIZE;
+/// Seconds an enemy charges up for after spotting a robot.
+const ALERT_TIME: f32 = 1.0;
+/// Seconds an enemy charges for before going back to patrolling.
+const CHARGE_TIME: f32 = 1.5;
+/// How many times faster than patrolling an enemy charges.
+const CHARGE_BOOST: f32 = 3.0;
+/// How far away a chasing enemy goes after robots.
+const CHASE_RANGE: f32 = 12.0 * BLOCK_SIZE;
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_enemy_textures).add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(update_enemies.before("collision"))
+                .with_system(enemy_contacts.after("collision")),
+        );
+    }
+}
+
+/// The enemy standing still, then charging up in five stages.
+pub struct EnemyTextures {
+    idle: Handle<Image>,
+    stages: Vec<Handle<Image>>,
+}
+
+fn load_enemy_textures(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(EnemyTextures {
+        idle: asset_server.load("enemy.png"),
+        stages: (1..=5)
+            .map(|stage| asset_server.load(&format!("enemy_stage{}.png", stage)))
+            .collect(),
+    });
+}
+
+#[derive(Debug, Default)]
+pub enum EnemyState {
+    #[default]
+    Patrol,
+    /// Spotted a robot and is charging up.
+    Alert(Timer),
+    /// Rushing the way the robot was when it finished charging up.
+    Charge(Vec3, Timer),
+}
+
+/// Patrols back and forth between walls, or along its [`PathFollower`] path
+/// when it has one, and kills robots it touches. Walking enemies that `chase` find their way
+/// to robots in range instead.
+#[derive(Component, Debug)]
+pub struct Enemy {
+    pub speed: f32,
+    /// Direction along the floor it walks in, 1 or -1.
+    pub direction: f32,
+    pub chase: bool,
+    pub state: EnemyState,
+    /// Way to the robot it is chasing, kept until it has to be planned again.
+    route: Option<Route>,
+}
+
+/// Steps planned toward the cell the chased robot was in.
+#[derive(Debug)]
+struct Route {
+    goal: IVec2,
+    /// Cell of the last step the enemy got to.
+    from: IVec2,
+    steps: Vec<Step>,
+}
+
+impl Enemy {
+    /// Next step on the way to `target`. The way is only planned again when
+    /// the target moves to another cell, the enemy lands somewhere off it or
+    /// the level changes.
+    fn chase_step(
+        &mut self,
+        navigation: &Res<Navigation>,
+        position: Vec3,
+        target: Option<Vec3>,
+        grounded: bool,
+    ) -> Option<Step> {
+        let target = match target {
+            Some(target) => target,
+            None => {
+                self.route = None;
+                return None;
+            }
+        };
+        let goal = Navigation::cell(target);
+        let cell = Navigation::cell(position);
+        let stale = match &self.route {
+            Some(route) => {
+                let off_route = grounded
+                    && cell != route.from
+                    && route
+                        .steps
+                        .iter()
+                        .all(|step| Navigation::cell(step.position) != cell);
+                route.goal != goal || off_route || navigation.is_changed()
+            }
+            None => true,
+        };
+        if stale {
+            self.route = Some(Route {
+                goal,
+                from: cell,
+                steps: navigation.find_path(position, target).unwrap_or_default(),
+            });
+        }
+
+        let route = self.route.as_mut()?;
+        let reached = route
+            .steps
+            .iter()
+            .position(|step| Navigation::cell(step.position) == cell);
+        if let Some(reached) = reached {
+            route.steps.drain(..=reached);
+            route.from = cell;
+        }
+        route.steps.first().copied()
+    }
+}
+
+#[derive(Bundle)]
+pub struct EnemyBundle {
+    #[bundle]
+    sprite_bundle: SpriteBundle,
+    enemy: Enemy,
+    collider: Collider,
+    velocity: Velocity,
+    path: PathFollower,
+    world_object: WorldObject,
+}
+
+impl EnemyBundle {
+    pub fn new(mut transform: Transform, speed: f32) -> Self {
+        let size = Vec2::splat(0.8 * BLOCK_SIZE);
+        transform.translation.z = 10.0;
+        Self {
+            sprite_bundle: SpriteBundle {
+                transform,
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    ..default()
+                },
+                ..default()
+            },
+            enemy: Enemy {
+                speed,
+                direction: 1.0,
+                chase: false,
+                state: EnemyState::Patrol,
+                route: None,
+            },
+            collider: Collider {
+                kind: ColliderKind::Movable(3.0),
+                size,
+                flags: CollisionFlags::empty(),
+            },
+            velocity: Velocity::default(),
+            path: PathFollower::default(),
+            world_object: WorldObject,
+        }
+    }
+
+    pub fn with_path(mut self, path: Vec<Vec3>) -> Self {
+        self.path = PathFollower::new(path, self.enemy.speed);
+        self
+    }
+
+    pub fn with_chase(mut self, chase: bool) -> Self {
+        self.enemy.chase = chase;
+        self
+    }
+}
+
+type EnemyQuery<'a> = (
+    &'a mut Enemy,
+    &'a Transform,
+    &'a Collider,
+    &'a mut Velocity,
+    Option<&'a Gravity>,
+    &'a mut Handle<Image>,
+    &'a mut Sprite,
+    &'a mut PathFollower,
+);
+
+fn update_enemies(
+    time: Res<Time>,
+    textures: Option<Res<EnemyTextures>>,
+    navigation: Option<Res<Navigation>>,
+    robots: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut enemies: Query<EnemyQuery, Without<Hacked>>,
+) {
+    let textures = match textures {
+        Some(textures) => textures,
+        None => return,
+    };
+    for (
+        mut enemy,
+        transform,
+        collider,
+        mut velocity,
+        gravity,
+        mut texture,
+        mut sprite,
+        mut path,
+    ) in enemies.iter_mut()
+    {
+        let enemy = &mut *enemy;
+        let position = transform.translation;
+        // enemies without gravity fly their path and move freely
+        let along = |direction: Vec3| match gravity {
+            Some(gravity) => gravity.right() * direction.dot(gravity.right()),
+            None => direction,
+        };
+        let walking = gravity.map(|gravity| gravity.right() * enemy.direction);
+        // the navigation graph only knows about falling down
+        let chase = match (&navigation, gravity) {
+            (Some(navigation), Some(gravity)) if enemy.chase && gravity.down().y < 0.0 => {
+                let target = robots
+                    .iter()
+                    .map(|robot| robot.translation)
+                    .filter(|robot| robot.distance(position) < CHASE_RANGE)
+                    .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+                let grounded = collider.flags.contains(gravity.floor());
+                enemy.chase_step(navigation, position, target, grounded)
+            }
+            _ => None,
+        };
+
+        let next_state = match &mut enemy.state {
+            EnemyState::Patrol => {
+                // robots ahead of it and roughly level with it
+                let spotted = robots.iter().any(|robot| {
+                    let offset = (robot.translation - position).truncate().extend(0.0);
+                    let ahead = along(offset);
+                    offset.length() < SIGHT
+                        && (offset - ahead).length() < BLOCK_SIZE
+                        && !matches!(walking, Some(walking) if ahead.dot(walking) <= 0.0)
+                });
+                spotted.then(|| EnemyState::Alert(Timer::from_seconds(ALERT_TIME, false)))
+            }
+            EnemyState::Alert(timer) => {
+                timer.tick(time.delta());
+                timer.finished().then(|| {
+                    let target = robots.iter().min_by(|a, b| {
+                        let a = a.translation.distance(position);
+                        let b = b.translation.distance(position);
+                        a.total_cmp(&b)
+                    });
+                    let direction = target
+                        .map(|robot| along(robot.translation - position).normalize_or_zero())
+                        .unwrap_or_default();
+                    EnemyState::Charge(direction, Timer::from_seconds(CHARGE_TIME, false))
+                })
+            }
+            EnemyState::Charge(direction, timer) => {
+                timer.tick(time.delta());
+                let blocked = collider.flags.contains(CollisionFlags::facing(*direction));
+                (timer.finished() || blocked).then_some(EnemyState::Patrol)
+            }
+        };
+        if let Some(state) = next_state {
+            enemy.state = state;
+        }
+
+        // only the velocity along the floor is the enemy's own, gravity does the rest
+        let mut fall = velocity.linvel - along(velocity.linvel);
+        let speed = enemy.speed;
+        let own = match &enemy.state {
+            EnemyState::Patrol => match (walking, chase) {
+                (Some(walking), Some(step)) => {
+                    let side = (step.position - position).dot(walking).signum();
+                    let grounded =
+                        gravity.is_some_and(|gravity| collider.flags.contains(gravity.floor()));
+                    if step.movement == Move::Jump && grounded {
+                        fall = Vec3::Y * navigation::JUMP_SPEED;
+                    }
+                    walking * side * speed
+                }
+                // turn around at walls
+                (Some(walking), None)
+                    if collider.flags.contains(CollisionFlags::facing(walking)) =>
+                {
+                    enemy.direction = -enemy.direction;
+                    -walking * speed
+                }
+                (Some(walking), None) => walking * speed,
+                (None, _) => {
+                    path.step(position, time.delta_seconds())
+                        .normalize_or_zero()
+                        * speed
+                }
+            },
+            EnemyState::Alert(_) => Vec3::ZERO,
+            EnemyState::Charge(direction, _) => *direction * speed * CHARGE_BOOST,
+        };
+        velocity.linvel = fall + own;
+
+        let shown = match &enemy.state {
+            EnemyState::Patrol => &textures.idle,
+            EnemyState::Alert(timer) => {
+                let stage = (timer.percent() * textures.stages.len() as f32) as usize;
+                &textures.stages[stage.min(textures.stages.len() - 1)]
+            }
+            EnemyState::Charge(..) => &textures.stages[textures.stages.len() - 1],
+        };
+        // assigning marks the sprite changed, even with the same handle
+        if *texture != *shown {
+            *texture = shown.clone_weak();
+        }
+        if own.x != 0.0 {
+            sprite.flip_x = own.x < 0.0;
+        }
+    }
+}
+
+fn enemy_contacts(
+    mut contacts: EventReader<Contact>,
+    mut events: EventWriter<GameEvent>,
+    enemies: Query<&Enemy, Without<Hacked>>,
+    players: Query<&Player>,
+) {
+    let killed = contacts.iter().any(|contact| {
+        (enemies.get(contact.entity).is_ok() && players.get(contact.other).is_ok())
+            || (players.get(contact.entity).is_ok() && enemies.get(contact.other).is_ok())
+    });
+    if killed {
+        events.send(GameEvent::Death);
+    }
+}
@@ -3,6 +3,7 @@ use bevy_ecs_tilemap::prelude::*;
 
 use crate::{
     collide::{Collider, CollisionFlags, GameEvent},
+    controls::Action,
     map::spawn_map,
     slider::Slider,
     state::GameState,
@@ -27,25 +28,29 @@ impl Plugin for EventPlugin {
 
 fn button_events(
     mut buttons: Query<(&Collider, &mut Button)>,
-    mut doors: Query<&mut Slider>,
+    mut outputs: Query<&mut Slider>,
     mut door_res: ResMut<DoorRes>,
 ) {
     for (collider, mut button) in buttons.iter_mut() {
-        let mut entry = door_res.0.get_mut(&button.door).unwrap();
-        let mut door = match doors.get_component_mut::<Slider>(entry.1) {
-            Ok(door) => door,
-            _ => return,
-        };
+        let entry = door_res.0.get_mut(&button.door).unwrap();
         if collider.flags != CollisionFlags::empty() && !button.is_pressed() {
             entry.0 -= 1;
             button.toggle();
             if entry.0 == 0 {
-                door.activated = true;
+                for &output in entry.1.iter() {
+                    if let Ok(mut slider) = outputs.get_mut(output) {
+                        slider.activated = true;
+                    }
+                }
             }
         } else if collider.flags == CollisionFlags::empty() && button.is_pressed() {
             entry.0 += 1;
             button.toggle();
-            door.activated = false;
+            for &output in entry.1.iter() {
+                if let Ok(mut slider) = outputs.get_mut(output) {
+                    slider.activated = false;
+                }
+            }
         }
     }
 }
@@ -57,7 +62,7 @@ fn handle_events(
     entities: Query<Entity, With<WorldObject>>,
     asset_server: Res<AssetServer>,
     mut level: ResMut<Level>,
-    input: Res<Input<KeyCode>>,
+    actions: Res<Input<Action>>,
 ) {
     let mut is_dead = false;
     let mut won = false;
@@ -68,7 +73,7 @@ fn handle_events(
         }
     }
 
-    if is_dead || input.just_pressed(KeyCode::R) {
+    if is_dead || actions.just_pressed(Action::Restart) {
         map_query.despawn(&mut commands, 0);
         for entity in entities.iter() {
             commands.entity(entity).despawn();
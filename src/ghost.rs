@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    collide::{map_dimensions, simulate, Body, Collider, CollisionFlags, Contact, Surface},
+    drone::apply_drone_input,
+    player::{Active, Footing, GameInput, Ladder, Link, Player, PlayerTexture},
+    state::GameState,
+    tiled_loader::TiledMap,
+    velocity::{Gravity, Velocity},
+    Difficulty,
+};
+
+/// Number of dots drawn along the predicted path.
+const TRAIL_LENGTH: usize = 16;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(spawn_ghost)
+                .with_system(update_ghost.after("collision")),
+        );
+    }
+}
+
+/// Translucent robot showing where the robot ends up once its queued inputs
+/// have arrived.
+#[derive(Component)]
+struct Ghost;
+
+/// Dot along the path the ghost takes to get there.
+#[derive(Component)]
+struct GhostTrail(usize);
+
+/// The last prediction and what it was made from, played again only once the
+/// robot or its queued inputs change.
+#[derive(Default)]
+struct Prediction {
+    robot: Option<(Entity, Vec3, Vec3)>,
+    frames: Vec<Vec<GameInput>>,
+    path: Vec<Vec3>,
+}
+
+fn spawn_ghost(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    player_texture: Option<Res<PlayerTexture>>,
+    ghosts: Query<&Ghost>,
+) {
+    let texture = match player_texture {
+        Some(texture) => texture,
+        None => return,
+    };
+    if !difficulty.ghost || !ghosts.is_empty() {
+        return;
+    }
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: texture.0.clone(),
+            sprite: TextureAtlasSprite {
+                color: Color::rgba(1.0, 1.0, 1.0, 0.4),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Ghost);
+    for i in 0..TRAIL_LENGTH {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 1.0, 1.0, 0.4),
+                    custom_size: Some(Vec2::splat(8.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(GhostTrail(i));
+    }
+}
+
+type BodyQuery<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Collider,
+    Option<&'a Velocity>,
+    Option<&'a Surface>,
+    Option<&'a Gravity>,
+    Option<&'a Ladder>,
+);
+
+type GhostQuery<'a> = (
+    &'a mut Transform,
+    &'a mut Visibility,
+    Option<&'a mut TextureAtlasSprite>,
+    Option<&'a GhostTrail>,
+);
+
+type GhostFilter = Or<(With<Ghost>, With<GhostTrail>)>;
+
+type RobotQuery<'a> = (Entity, &'a Player, &'a Link, &'a TextureAtlasSprite);
+
+#[allow(clippy::too_many_arguments)]
+fn update_ghost(
+    difficulty: Res<Difficulty>,
+    player_query: Query<RobotQuery, (With<Active>, Without<Ghost>)>,
+    bodies: Query<BodyQuery, (Without<Ghost>, Without<GhostTrail>)>,
+    mut ghosts: Query<GhostQuery, GhostFilter>,
+    mut prediction: Local<Prediction>,
+    time: Res<Time>,
+    assets: Res<Assets<TiledMap>>,
+    map_query: Query<&Handle<TiledMap>>,
+) {
+    let player = match player_query.get_single() {
+        Ok(player) if difficulty.ghost => player,
+        _ => {
+            for (_, mut visibility, _, _) in ghosts.iter_mut() {
+                visibility.is_visible = false;
+            }
+            return;
+        }
+    };
+    let (robot, player, link, robot_sprite) = player;
+    let dimensions = match map_query.get_single() {
+        Ok(handle) => map_dimensions(assets.get(handle)),
+        _ => return,
+    };
+    let body = |(entity, transform, collider, velocity, surface, gravity, _): BodyQuery| Body {
+        entity,
+        position: transform.translation,
+        collider: *collider,
+        velocity: velocity.cloned(),
+        surface: surface.copied(),
+        gravity: gravity.copied(),
+    };
+    let robot_body = match bodies.get(robot) {
+        Ok(robot) => body(robot),
+        Err(_) => return,
+    };
+
+    let state = Some((
+        robot,
+        robot_body.position,
+        robot_body
+            .velocity
+            .as_ref()
+            .map_or(Vec3::ZERO, |velocity| velocity.linvel),
+    ));
+    let frames = link.pending_frames();
+    if prediction.robot != state || prediction.frames != frames {
+        // only the robot moves, against everything that stays put
+        let obstacles: Vec<Body> = bodies
+            .iter()
+            .filter(|(_, _, _, velocity, ..)| velocity.is_none())
+            .map(body)
+            .collect();
+        let ladders: Vec<Entity> = bodies
+            .iter()
+            .filter(|(.., ladder)| ladder.is_some())
+            .map(|(entity, ..)| entity)
+            .collect();
+        let load = player
+            .holding
+            .and_then(|held| bodies.get(held).ok())
+            .map_or(0.0, |(_, _, collider, ..)| collider.weight());
+        prediction.path = predict(
+            (&robot_body, player.clone(), load),
+            &obstacles,
+            &ladders,
+            &frames,
+            dimensions,
+            time.delta_seconds(),
+        );
+        prediction.robot = state;
+        prediction.frames = frames;
+    }
+    let path = &prediction.path;
+    let gravity = robot_body.gravity.unwrap_or_default();
+
+    for (mut transform, mut visibility, sprite, trail) in ghosts.iter_mut() {
+        match trail {
+            Some(GhostTrail(i)) => {
+                // spread the dots evenly over the whole path
+                let point = (i + 1) * path.len() / TRAIL_LENGTH;
+                visibility.is_visible = point > 0 && point < path.len();
+                if visibility.is_visible {
+                    transform.translation = path[point].truncate().extend(150.0);
+                }
+            }
+            None => {
+                visibility.is_visible = !path.is_empty();
+                if let Some(end) = path.last() {
+                    transform.translation = end.truncate().extend(150.0);
+                    transform.rotation = gravity.rotation();
+                }
+                if let Some(mut sprite) = sprite {
+                    sprite.index = robot_sprite.index;
+                    sprite.flip_x = robot_sprite.flip_x;
+                    sprite.custom_size = robot_sprite.custom_size;
+                }
+            }
+        }
+    }
+}
+
+/// Plays `frames` of inputs on `robot` among `obstacles` that don't move,
+/// one frame per physics step of `delta` seconds, and returns where the robot
+/// is after each of them. Robots move the way [`Player::steer`] moves them,
+/// carrying a `load` and climbing `ladders`. It only falls with the gravity
+/// it starts with, without zones changing it along the way.
+pub fn predict(
+    (robot, mut player, load): (&Body, Player, f32),
+    obstacles: &[Body],
+    ladders: &[Entity],
+    frames: &[Vec<GameInput>],
+    dimensions: (f32, f32),
+    delta: f32,
+) -> Vec<Vec3> {
+    let mut bodies = vec![robot.clone()];
+    bodies.extend_from_slice(obstacles);
+    let surfaces: HashMap<Entity, Surface> = obstacles
+        .iter()
+        .filter_map(|body| Some((body.entity, body.surface?)))
+        .collect();
+    let floor = robot.gravity.unwrap_or_default().floor();
+
+    let mut contacts: Vec<Contact> = vec![];
+    let mut path = Vec::with_capacity(frames.len());
+    for frame in frames {
+        let body = &mut bodies[0];
+        let flags = body.collider.flags;
+        if let Some(velocity) = body.velocity.as_mut() {
+            match &body.gravity {
+                Some(gravity) => {
+                    velocity.apply_force(gravity.0);
+                    let footing = Footing {
+                        on_floor: flags.contains(floor),
+                        ladder: contacts
+                            .iter()
+                            .any(|contact| ladders.contains(&contact.other)),
+                        wall: [-gravity.right(), gravity.right()]
+                            .into_iter()
+                            .find(|side| flags.contains(CollisionFlags::facing(*side))),
+                        traction: contacts
+                            .iter()
+                            .filter(|contact| contact.side.contains(floor))
+                            .find_map(|contact| surfaces.get(&contact.other))
+                            .map_or(1.0, |surface| surface.friction),
+                    };
+                    player.steer(frame, velocity, gravity, footing, load, delta);
+                }
+                // drones are the robots without gravity
+                None => {
+                    for input in frame {
+                        apply_drone_input(input, velocity);
+                    }
+                }
+            }
+        }
+
+        contacts = simulate(&mut bodies, dimensions, delta);
+        contacts.retain(|contact| contact.entity == robot.entity);
+        path.push(bodies[0].position);
+    }
+    path
+}
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+
+use crate::{
+    collide::Collider,
+    controls::Action,
+    enemy::{Enemy, EnemyState},
+    map::{CellTower, BLOCK_SIZE},
+    network::LevelSignal,
+    player::{nearest_tower, Active, GameInput, Link, Player, Signal, MAX_LATENCY},
+    state::GameState,
+    velocity::{Gravity, Velocity},
+};
+
+/// How close an enemy has to be for a robot to hack it.
+const HACK_RANGE: f32 = 3.0 * BLOCK_SIZE;
+/// Speed a hacked enemy jumps off the floor with.
+const JUMP_SPEED: f32 = 1800.0;
+
+pub struct HackPlugin;
+
+impl Plugin for HackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(hack_enemies)
+                .with_system(release_lost_enemies)
+                .with_system(drive_hacked_enemies.before("collision")),
+        );
+    }
+}
+
+/// Enemy taken over by `robot`, which stands idle until it lets go.
+#[derive(Component, Debug)]
+pub struct Hacked {
+    pub robot: Entity,
+}
+
+/// Hands control back from a hacked enemy to the robot that hacked it.
+fn release(commands: &mut Commands, enemy: Entity, robot: Entity) {
+    commands
+        .entity(enemy)
+        .remove::<Hacked>()
+        .remove::<Link>()
+        .remove::<Active>();
+    commands.entity(robot).insert(Active);
+}
+
+/// Whether an enemy at `position` has signal of its own to be controlled
+/// through. Levels without towers have none anywhere.
+fn has_signal<'a>(
+    position: Vec3,
+    towers: impl Iterator<Item = (&'a Transform, &'a CellTower)>,
+) -> bool {
+    nearest_tower(position, towers).is_some_and(|(latency, _)| latency <= MAX_LATENCY)
+}
+
+type ActiveRobot = (With<Player>, With<Active>);
+type FreeEnemy = (With<Enemy>, Without<Hacked>);
+
+fn hack_enemies(
+    mut commands: Commands,
+    actions: Res<Input<Action>>,
+    robots: Query<(Entity, &Transform), ActiveRobot>,
+    hacked: Query<(Entity, &Hacked), With<Active>>,
+    enemies: Query<(Entity, &Transform), FreeEnemy>,
+    towers: Query<(&Transform, &CellTower)>,
+) {
+    if !actions.just_pressed(Action::Hack) {
+        return;
+    }
+    if let Ok((enemy, hacked)) = hacked.get_single() {
+        release(&mut commands, enemy, hacked.robot);
+        return;
+    }
+    let (robot, robot_transform) = match robots.get_single() {
+        Ok(robot) => robot,
+        _ => return,
+    };
+    let position = robot_transform.translation;
+    let target = enemies
+        .iter()
+        .filter(|(_, transform)| {
+            transform.translation.distance(position) <= HACK_RANGE
+                && has_signal(transform.translation, towers.iter())
+        })
+        .min_by(|(_, a), (_, b)| {
+            let a = a.translation.distance(position);
+            let b = b.translation.distance(position);
+            a.total_cmp(&b)
+        });
+    if let Some((enemy, _)) = target {
+        commands.entity(robot).remove::<Active>();
+        commands
+            .entity(enemy)
+            .insert(Hacked { robot })
+            .insert(Link::default())
+            .insert(Active);
+    }
+}
+
+/// Lets go of hacked enemies that left tower coverage.
+fn release_lost_enemies(mut commands: Commands, hacked: Query<(Entity, &Hacked, &Link)>) {
+    for (enemy, hacked, link) in hacked.iter() {
+        if link.signal == Signal::Disconnected {
+            release(&mut commands, enemy, hacked.robot);
+        }
+    }
+}
+
+type HackedQuery<'a> = (
+    &'a mut Link,
+    &'a mut Enemy,
+    &'a Collider,
+    &'a mut Velocity,
+    Option<&'a Gravity>,
+    &'a mut Sprite,
+);
+
+/// Moves hacked enemies by the inputs that reach them instead of their
+/// patrol. Walking enemies walk and jump, flying ones fly every way.
+fn drive_hacked_enemies(
+    time: Res<Time>,
+    level_signal: Res<LevelSignal>,
+    mut hacked: Query<HackedQuery, With<Hacked>>,
+) {
+    for (mut link, mut enemy, collider, mut velocity, gravity, mut sprite) in hacked.iter_mut() {
+        enemy.state = EnemyState::Patrol;
+        let inputs = link.receive(time.delta_seconds(), &level_signal);
+        let own = match gravity {
+            Some(gravity) => {
+                let mut walking = Vec3::ZERO;
+                for input in inputs.iter() {
+                    match input {
+                        GameInput::Left => walking = -gravity.right(),
+                        GameInput::Right => walking = gravity.right(),
+                        GameInput::Jump if collider.flags.contains(gravity.floor()) => {
+                            velocity.linvel -= gravity.down() * JUMP_SPEED;
+                        }
+                        _ => {}
+                    }
+                }
+                // gravity keeps whatever it does across the floor
+                let right = gravity.right();
+                let across = velocity.linvel.dot(right);
+                velocity.linvel -= right * across;
+                walking
+            }
+            None => {
+                let direction: Vec3 = inputs
+                    .iter()
+                    .map(|input| match input {
+                        GameInput::Left => -Vec3::X,
+                        GameInput::Right => Vec3::X,
+                        GameInput::Jump | GameInput::HoldJump | GameInput::Up => Vec3::Y,
+                        GameInput::Down => -Vec3::Y,
+                        GameInput::Interact => Vec3::ZERO,
+                    })
+                    .fold(Vec3::ZERO, |sum, direction| sum + direction);
+                velocity.linvel = Vec3::ZERO;
+                direction.normalize_or_zero()
+            }
+        };
+        if link.is_frozen(&level_signal) {
+            continue;
+        }
+        velocity.linvel += own * enemy.speed;
+        if own.x != 0.0 {
+            sprite.flip_x = own.x < 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enemies_have_no_signal_without_towers() {
+        assert!(!has_signal(Vec3::ZERO, std::iter::empty()));
+    }
+
+    #[test]
+    fn enemies_have_signal_near_a_tower() {
+        let tower = (Transform::default(), CellTower::default());
+        let near = Vec3::new(BLOCK_SIZE, 0.0, 0.0);
+        assert!(has_signal(near, std::iter::once((&tower.0, &tower.1))));
+        let far = Vec3::new(BLOCK_SIZE * MAX_LATENCY as f32, 0.0, 0.0);
+        assert!(!has_signal(far, std::iter::once((&tower.0, &tower.1))));
+    }
+}
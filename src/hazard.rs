@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use crate::{
+    collide::{Collider, ColliderKind},
+    map::BLOCK_SIZE,
+    player::Player,
+    slider::{is_switched_on, Slider},
+    state::GameState,
+};
+
+/// Distance between the points checked along a beam for something blocking it.
+const BEAM_STEP: f32 = BLOCK_SIZE / 8.0;
+/// Thickness of a laser beam.
+const BEAM_WIDTH: f32 = BLOCK_SIZE / 8.0;
+/// Turns per second of a saw blade.
+const SAW_SPIN: f32 = 2.0;
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(update_lasers.before("collision"))
+                .with_system(spin_saws),
+        );
+    }
+}
+
+/// Shoots a beam that kills robots, up to `range` or the first solid thing in
+/// its way, boxes included.
+#[derive(Component, Debug)]
+pub struct Laser {
+    pub direction: Vec3,
+    pub range: f32,
+    /// Circuit output that switches the laser.
+    pub switch: Option<Entity>,
+    /// Whether the circuit turns it on rather than off.
+    pub powered: bool,
+    /// Seconds it stays on and then off for, over and over, it stays on when
+    /// `None`.
+    pub blink: Option<(f32, f32)>,
+    /// Seconds since the level started, for blinking.
+    pub clock: f32,
+    pub beam: Entity,
+}
+
+impl Laser {
+    fn is_on(&self, sliders: &Query<&Slider>) -> bool {
+        let lit = match self.blink {
+            Some((on, off)) => self.clock % (on + off) < on,
+            None => true,
+        };
+        is_switched_on(self.switch, self.powered, sliders) && lit
+    }
+}
+
+/// The beam of a laser, stretched every frame to what it reaches.
+#[derive(Component, Debug)]
+pub struct LaserBeam;
+
+/// Spinning blade that kills robots, following its [`PathFollower`] when it
+/// has one.
+///
+/// [`PathFollower`]: crate::path::PathFollower
+#[derive(Component, Debug)]
+pub struct Saw;
+
+/// How far a beam from `start` toward `direction` gets before it hits one of
+/// the `obstacles`.
+fn beam_length(start: Vec3, direction: Vec3, range: f32, obstacles: &[(Vec3, Vec2)]) -> f32 {
+    let steps = (range / BEAM_STEP).ceil() as usize;
+    (0..=steps)
+        .map(|i| (i as f32 * BEAM_STEP).min(range))
+        .find(|&distance| {
+            let point = (start + direction * distance).truncate();
+            obstacles.iter().any(|(position, size)| {
+                let offset = (point - position.truncate()).abs();
+                offset.x < size.x / 2.0 && offset.y < size.y / 2.0
+            })
+        })
+        .unwrap_or(range)
+}
+
+type BeamQuery<'a> = (
+    &'a mut Transform,
+    &'a mut Collider,
+    &'a mut Sprite,
+    &'a mut Visibility,
+);
+
+/// What can stand in the way of a beam, robots walk into it instead.
+type BeamObstacle = (Without<Player>, Without<LaserBeam>);
+
+fn update_lasers(
+    time: Res<Time>,
+    sliders: Query<&Slider>,
+    colliders: Query<(Entity, &Transform, &Collider), BeamObstacle>,
+    mut lasers: Query<(Entity, &Transform, &Collider, &mut Laser)>,
+    mut beams: Query<BeamQuery, (With<LaserBeam>, Without<Laser>)>,
+) {
+    let obstacles: Vec<(Entity, Vec3, Vec2)> = colliders
+        .iter()
+        .filter(|(_, _, collider)| matches!(collider.kind, ColliderKind::Movable(_)))
+        .map(|(entity, transform, collider)| (entity, transform.translation, collider.size))
+        .collect();
+    for (entity, transform, collider, mut laser) in lasers.iter_mut() {
+        laser.clock += time.delta_seconds();
+        let on = laser.is_on(&sliders);
+        let (mut beam_transform, mut beam_collider, mut sprite, mut visibility) =
+            match beams.get_mut(laser.beam) {
+                Ok(beam) => beam,
+                Err(_) => continue,
+            };
+        visibility.is_visible = on;
+        if !on {
+            beam_collider.kind = ColliderKind::Sensor;
+            beam_collider.size = Vec2::ZERO;
+            continue;
+        }
+
+        let others: Vec<(Vec3, Vec2)> = obstacles
+            .iter()
+            .filter(|(obstacle, ..)| *obstacle != entity)
+            .map(|(_, position, size)| (*position, *size))
+            .collect();
+        let start = transform.translation
+            + laser.direction * (laser.direction.truncate() * collider.size / 2.0).length();
+        let length = beam_length(start, laser.direction, laser.range, &others);
+        let size = (laser.direction.truncate().abs() * length).max(Vec2::splat(BEAM_WIDTH));
+        beam_transform.translation = (start + laser.direction * length / 2.0)
+            .truncate()
+            .extend(15.0);
+        beam_collider.kind = ColliderKind::Death;
+        beam_collider.size = size;
+        sprite.custom_size = Some(size);
+    }
+}
+
+fn spin_saws(time: Res<Time>, mut saws: Query<&mut Transform, With<Saw>>) {
+    for mut transform in saws.iter_mut() {
+        transform.rotate(Quat::from_rotation_z(
+            -SAW_SPIN * std::f32::consts::TAU * time.delta_seconds(),
+        ));
+    }
+}
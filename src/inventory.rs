@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::{
+    collide::{Collider, Contact},
+    navigation::Navigation,
+    state::GameState,
+};
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(collect_keys.after("collision"))
+                .with_system(open_locks.after("collision")),
+        );
+    }
+}
+
+/// Keys a robot has picked up, by id. Robots are spawned again with an empty
+/// one when the level restarts.
+#[derive(Component, Debug, Default)]
+pub struct Inventory {
+    pub keys: Vec<String>,
+}
+
+/// Picked up by the first robot to touch it.
+#[derive(Component, Debug)]
+pub struct Key {
+    pub id: String,
+}
+
+/// Door that opens for good once a robot carrying a key with the same id
+/// touches it, using up the key. Enemies find their way through it once it
+/// is open.
+#[derive(Component, Debug)]
+pub struct Lock {
+    pub id: String,
+}
+
+fn collect_keys(
+    mut commands: Commands,
+    mut contacts: EventReader<Contact>,
+    keys: Query<&Key>,
+    mut robots: Query<&mut Inventory>,
+) {
+    let mut collected = vec![];
+    for contact in contacts.iter() {
+        let (mut inventory, key) = match (robots.get_mut(contact.entity), keys.get(contact.other)) {
+            (Ok(inventory), Ok(key)) => (inventory, key),
+            _ => continue,
+        };
+        // two robots can touch the same key at once
+        if collected.contains(&contact.other) {
+            continue;
+        }
+        collected.push(contact.other);
+        inventory.keys.push(key.id.clone());
+        commands.entity(contact.other).despawn();
+    }
+}
+
+fn open_locks(
+    mut commands: Commands,
+    mut contacts: EventReader<Contact>,
+    locks: Query<(&Lock, &Transform, &Collider)>,
+    mut robots: Query<&mut Inventory>,
+    mut navigation: Option<ResMut<Navigation>>,
+) {
+    let mut opened = vec![];
+    for contact in contacts.iter() {
+        let (mut inventory, (lock, transform, collider)) =
+            match (robots.get_mut(contact.entity), locks.get(contact.other)) {
+                (Ok(inventory), Ok(lock)) => (inventory, lock),
+                _ => continue,
+            };
+        if opened.contains(&contact.other) {
+            continue;
+        }
+        if let Some(index) = inventory.keys.iter().position(|id| *id == lock.id) {
+            inventory.keys.remove(index);
+            opened.push(contact.other);
+            commands.entity(contact.other).despawn();
+            if let Some(navigation) = navigation.as_mut() {
+                for cell in Navigation::covered(transform.translation, collider.size) {
+                    navigation.set_free(Navigation::center(cell));
+                }
+            }
+        }
+    }
+    if !opened.is_empty() {
+        if let Some(navigation) = navigation.as_mut() {
+            navigation.connect();
+        }
+    }
+}
@@ -4,34 +4,78 @@ use std::collections::HashMap;
 use animation::AnimationPlugin;
 use bevy::{prelude::*, window::WindowMode};
 use bevy_ecs_tilemap::TilemapPlugin;
+use breakable::BreakablePlugin;
 use camera::CameraPlugin;
+use carry::CarryPlugin;
 use collide::{CollidePlugin, GameEvent};
+use controls::ControlsPlugin;
+use drone::DronePlugin;
+use enemy::EnemyPlugin;
 use event::EventPlugin;
+use ghost::GhostPlugin;
+use hack::HackPlugin;
+use hazard::HazardPlugin;
+use inventory::InventoryPlugin;
 use main_menu::MainMenuPlugin;
 use map::MapPlugin;
+use network::NetworkPlugin;
+use path::PathPlugin;
 use player::PlayerPlugin;
 use slider::SliderPlugin;
 use state::GameState;
+use teleport::TeleportPlugin;
 use tiled_loader::TiledMapPlugin;
 use trigger::DoorRes;
+use turret::TurretPlugin;
 use ui::UiPlugin;
 use velocity::VelocityPlugin;
 
 mod animation;
+mod breakable;
 mod camera;
+mod carry;
 mod collide;
+mod controls;
+mod drone;
+mod enemy;
 mod event;
+mod ghost;
+mod hack;
+mod hazard;
+mod inventory;
 mod main_menu;
 mod map;
+mod navigation;
+mod network;
+mod path;
 mod player;
 mod slider;
 mod state;
+mod teleport;
 mod tiled_loader;
 mod trigger;
+mod turret;
 mod ui;
 mod velocity;
 
 pub struct Level(u32);
+
+/// Player aids that can be turned off for a harder game.
+pub struct Difficulty {
+    /// Show the inputs that are still on their way to the robot.
+    pub show_queue: bool,
+    /// Show a ghost of where the robot ends up once its queued inputs land.
+    pub ghost: bool,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty {
+            show_queue: true,
+            ghost: false,
+        }
+    }
+}
 fn main() {
     App::new()
         .insert_resource(WindowDescriptor {
@@ -42,7 +86,6 @@ fn main() {
         })
         .add_state(GameState::MainMenu)
         .add_plugins(DefaultPlugins)
-        .add_system(bevy::input::system::exit_on_esc_system)
         .add_event::<GameEvent>()
         .add_plugin(TilemapPlugin)
         .add_plugin(TiledMapPlugin)
@@ -56,8 +99,22 @@ fn main() {
         .add_plugin(EventPlugin)
         .add_plugin(MainMenuPlugin)
         .add_plugin(SliderPlugin)
+        .add_plugin(BreakablePlugin)
+        .add_plugin(NetworkPlugin)
+        .add_plugin(GhostPlugin)
+        .add_plugin(CarryPlugin)
+        .add_plugin(DronePlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(HackPlugin)
+        .add_plugin(TurretPlugin)
+        .add_plugin(HazardPlugin)
+        .add_plugin(PathPlugin)
+        .add_plugin(TeleportPlugin)
+        .add_plugin(InventoryPlugin)
         .insert_resource(DoorRes(HashMap::new()))
         .insert_resource(Level(0))
+        .init_resource::<Difficulty>()
         .insert_resource(Msaa { samples: 1 })
         .add_startup_system(setup)
         .run();
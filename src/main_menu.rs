@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::{state::GameState, ui::UiButton};
+use crate::{state::GameState, ui::UiButton, Difficulty};
 
 pub struct MainMenuPlugin;
 
@@ -13,17 +13,34 @@ impl Plugin for MainMenuPlugin {
             .add_system_set(
                 SystemSet::on_exit(GameState::MainMenu).with_system(destroy_play_button),
             )
-            .add_system_set(SystemSet::on_enter(GameState::Pause).with_system(build_play_button))
             .add_system_set(
-                SystemSet::on_update(GameState::Pause).with_system(play_button_interaction),
+                SystemSet::on_enter(GameState::Pause)
+                    .with_system(build_play_button)
+                    .with_system(build_difficulty_toggles),
             )
-            .add_system_set(SystemSet::on_exit(GameState::Pause).with_system(destroy_play_button));
+            .add_system_set(
+                SystemSet::on_update(GameState::Pause)
+                    .with_system(play_button_interaction)
+                    .with_system(difficulty_toggle_interaction),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Pause)
+                    .with_system(destroy_play_button)
+                    .with_system(destroy_difficulty_toggles),
+            );
     }
 }
 
 #[derive(Component)]
 pub struct PlayButton;
 
+/// Pause menu button that turns one of the player aids on or off.
+#[derive(Component, Clone, Copy)]
+enum DifficultyToggle {
+    Queue,
+    Ghost,
+}
+
 fn play_button_interaction(
     mut state: ResMut<State<GameState>>,
     interaction_query: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
@@ -68,3 +85,90 @@ fn destroy_play_button(mut commands: Commands, ui_components: Query<Entity, With
         commands.entity(entity).despawn_recursive();
     }
 }
+
+impl DifficultyToggle {
+    fn label(self, difficulty: &Difficulty) -> String {
+        let (name, on) = match self {
+            DifficultyToggle::Queue => ("Input queue", difficulty.show_queue),
+            DifficultyToggle::Ghost => ("Ghost", difficulty.ghost),
+        };
+        format!("{}: {}", name, if on { "on" } else { "off" })
+    }
+
+    fn toggle(self, difficulty: &mut Difficulty) {
+        match self {
+            DifficultyToggle::Queue => difficulty.show_queue = !difficulty.show_queue,
+            DifficultyToggle::Ghost => difficulty.ghost = !difficulty.ghost,
+        }
+    }
+}
+
+fn build_difficulty_toggles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
+) {
+    let toggles = [DifficultyToggle::Queue, DifficultyToggle::Ghost];
+    for (i, toggle) in toggles.into_iter().enumerate() {
+        commands
+            .spawn_bundle(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        bottom: Val::Px(100.0 + 60.0 * i as f32),
+                        left: Val::Px(15.0),
+                        ..default()
+                    },
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(UiButton)
+            .insert(toggle)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        toggle.label(&difficulty),
+                        TextStyle {
+                            font: asset_server.load("VT323-Regular.ttf"),
+                            font_size: 40.0,
+                            color: Color::BLACK,
+                        },
+                        default(),
+                    ),
+                    ..default()
+                });
+            });
+    }
+}
+
+type ToggleInteraction<'a> = (&'a Interaction, &'a DifficultyToggle, &'a Children);
+
+fn difficulty_toggle_interaction(
+    mut difficulty: ResMut<Difficulty>,
+    interaction_query: Query<ToggleInteraction, Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, toggle, children) in interaction_query.iter() {
+        if interaction == &Interaction::Clicked {
+            toggle.toggle(&mut difficulty);
+            for &child in children.iter() {
+                if let Ok(mut text) = text_query.get_mut(child) {
+                    text.sections[0].value = toggle.label(&difficulty);
+                }
+            }
+        }
+    }
+}
+
+fn destroy_difficulty_toggles(
+    mut commands: Commands,
+    ui_components: Query<Entity, With<DifficultyToggle>>,
+) {
+    for entity in ui_components.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
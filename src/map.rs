@@ -2,6 +2,7 @@ use bevy::{prelude::*, text::Text2dBounds};
 use bevy_ecs_tilemap::prelude::*;
 
 use crate::{
+    controls::{Action, ActionMap},
     state::GameState,
     tiled_loader::{TiledMap, TiledMapBundle, WorldObject},
     Level,
@@ -13,15 +14,19 @@ pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameState::Play).with_system(spawn_map));
+        app.add_system_set(SystemSet::on_enter(GameState::Play).with_system(spawn_map))
+            .add_system_set(SystemSet::on_update(GameState::Play).with_system(spawn_hints));
     }
 }
 
 #[derive(Component)]
 pub struct ExitDoor;
 
-#[derive(Component)]
-pub struct CellTower;
+#[derive(Component, Default)]
+pub struct CellTower {
+    /// How many ticks an input sent through this tower can arrive early or late.
+    pub jitter: usize,
+}
 
 pub fn spawn_map(level: ResMut<Level>, mut commands: Commands, asset_server: Res<AssetServer>) {
     let handle: Handle<TiledMap> = asset_server.load(format!("levels/{}.tmx", level.0).as_str());
@@ -37,6 +42,20 @@ pub fn spawn_map(level: ResMut<Level>, mut commands: Commands, asset_server: Res
         },
         ..default()
     });
+}
+
+/// Tells the player what to do on the levels that teach something, using the
+/// keys they have bound. Runs once the level's map is spawned.
+fn spawn_hints(
+    mut commands: Commands,
+    level: Res<Level>,
+    asset_server: Res<AssetServer>,
+    actions: Res<ActionMap>,
+    maps: Query<(), Added<Handle<TiledMap>>>,
+) {
+    if maps.is_empty() {
+        return;
+    }
     let mut spawn = |text: &str| {
         commands
             .spawn_bundle(Text2dBundle {
@@ -69,9 +88,17 @@ pub fn spawn_map(level: ResMut<Level>, mut commands: Commands, asset_server: Res
     };
 
     match level.0 {
-        0 => spawn("WASD to move"),
+        0 => spawn(&format!(
+            "{} and {} to move, {} to jump",
+            actions.key(Action::Left),
+            actions.key(Action::Right),
+            actions.key(Action::Jump)
+        )),
         1 => spawn("Latency will delay your inputs.\nMove Carefully."),
-        4 => spawn("Press R to restart the level."),
+        4 => spawn(&format!(
+            "Press {} to restart the level.",
+            actions.key(Action::Restart)
+        )),
         8 => spawn("You Win!\nThanks for playing"),
         _ => (),
     };
@@ -0,0 +1,324 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use bevy::prelude::*;
+
+use crate::map::BLOCK_SIZE;
+
+/// Take-off speed the jumps in the graph are made with, the same as a robot's.
+pub const JUMP_SPEED: f32 = 2300.0;
+/// Rows a body can jump up onto a ledge, a little under the highest jump.
+const JUMP_HEIGHT: i32 = 3;
+/// Columns a body can cover in a jump.
+const JUMP_DISTANCE: i32 = 3;
+
+/// How a body gets from one cell to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Walk,
+    /// Walks off a ledge and drops onto the floor below.
+    Fall,
+    /// Jumps up a ledge or across a gap.
+    Jump,
+}
+
+/// A cell on the way to the goal and how it is reached from the one before.
+#[derive(Debug, Clone, Copy)]
+pub struct Step {
+    /// World position of the center of the cell.
+    pub position: Vec3,
+    pub movement: Move,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    to: usize,
+    movement: Move,
+    cost: u32,
+}
+
+/// Walkability graph of the static tiles of the level for bodies the size of
+/// a tile falling down. Cells count from the bottom left of the map and a
+/// body can stand in a free cell with a solid one below it.
+#[derive(Debug, Default)]
+pub struct Navigation {
+    width: i32,
+    height: i32,
+    solid: Vec<bool>,
+    /// Cells that kill whatever enters them, like spikes.
+    hazard: Vec<bool>,
+    edges: Vec<Vec<Edge>>,
+}
+
+impl Navigation {
+    pub fn new(width: u32, height: u32) -> Self {
+        let cells = (width * height) as usize;
+        Navigation {
+            width: width as i32,
+            height: height as i32,
+            solid: vec![false; cells],
+            hazard: vec![false; cells],
+            edges: vec![],
+        }
+    }
+
+    /// Cell the world position lies in.
+    pub fn cell(position: Vec3) -> IVec2 {
+        (position.truncate() / BLOCK_SIZE).floor().as_ivec2()
+    }
+
+    /// World position of the center of the cell.
+    pub fn center(cell: IVec2) -> Vec3 {
+        ((cell.as_vec2() + 0.5) * BLOCK_SIZE).extend(0.0)
+    }
+
+    /// Cells an area of `size` centered on `position` covers, at least the one
+    /// the position is in.
+    pub fn covered(position: Vec3, size: Vec2) -> impl Iterator<Item = IVec2> {
+        let min = Self::cell(position - (size / 2.0).extend(0.0));
+        let max = ((position.truncate() + size / 2.0) / BLOCK_SIZE)
+            .ceil()
+            .as_ivec2()
+            - IVec2::ONE;
+        let max = max.max(min);
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        let inside = (0..self.width).contains(&cell.x) && (0..self.height).contains(&cell.y);
+        inside.then(|| (cell.y * self.width + cell.x) as usize)
+    }
+
+    fn cell_at(&self, index: usize) -> IVec2 {
+        IVec2::new(index as i32 % self.width, index as i32 / self.width)
+    }
+
+    pub fn set_solid(&mut self, position: Vec3) {
+        if let Some(index) = self.index(Self::cell(position)) {
+            self.solid[index] = true;
+        }
+    }
+
+    /// Clears a cell that was solid, like a door that opened. Call
+    /// [`Navigation::connect`] again once done.
+    pub fn set_free(&mut self, position: Vec3) {
+        if let Some(index) = self.index(Self::cell(position)) {
+            self.solid[index] = false;
+        }
+    }
+
+    pub fn set_hazard(&mut self, position: Vec3) {
+        if let Some(index) = self.index(Self::cell(position)) {
+            self.hazard[index] = true;
+        }
+    }
+
+    /// Whether the cell is blocked, everything outside the map is.
+    pub fn is_solid(&self, cell: IVec2) -> bool {
+        match self.index(cell) {
+            Some(index) => self.solid[index],
+            None => true,
+        }
+    }
+
+    fn is_free(&self, cell: IVec2) -> bool {
+        self.index(cell)
+            .is_some_and(|index| !self.solid[index] && !self.hazard[index])
+    }
+
+    pub fn is_standable(&self, cell: IVec2) -> bool {
+        self.is_free(cell) && self.is_solid(cell - IVec2::Y)
+    }
+
+    /// Whether every cell between `from` and `to` in a row or column is free.
+    fn is_clear(&self, from: IVec2, to: IVec2) -> bool {
+        let step = (to - from).signum();
+        let mut cell = from;
+        while cell != to {
+            if !self.is_free(cell) {
+                return false;
+            }
+            cell += step;
+        }
+        self.is_free(to)
+    }
+
+    /// Floor a body in `cell` lands on when it drops straight down.
+    fn landing(&self, mut cell: IVec2) -> Option<IVec2> {
+        while self.is_free(cell) {
+            if self.is_standable(cell) {
+                return Some(cell);
+            }
+            cell -= IVec2::Y;
+        }
+        None
+    }
+
+    /// Links every cell a body can stand in to the ones it can walk, fall or
+    /// jump to. Call it once all the static tiles are in.
+    pub fn connect(&mut self) {
+        self.edges = (0..self.solid.len())
+            .map(|index| {
+                let cell = self.cell_at(index);
+                if self.is_standable(cell) {
+                    self.moves(cell)
+                } else {
+                    vec![]
+                }
+            })
+            .collect();
+    }
+
+    fn moves(&self, from: IVec2) -> Vec<Edge> {
+        let mut edges = vec![];
+        let mut push = |to: IVec2, movement, cost| {
+            if let Some(to) = self.index(to) {
+                edges.push(Edge { to, movement, cost });
+            }
+        };
+        for side in [-1, 1] {
+            let next = from + IVec2::X * side;
+            if self.is_standable(next) {
+                push(next, Move::Walk, 2);
+            } else if let Some(landing) = self.is_free(next).then(|| self.landing(next)).flatten() {
+                push(landing, Move::Fall, 2 + (from.y - landing.y) as u32);
+            }
+        }
+        for dx in -JUMP_DISTANCE..=JUMP_DISTANCE {
+            for dy in -JUMP_HEIGHT..=JUMP_HEIGHT {
+                // straight up lands back where it started, next door is walked to
+                if dx == 0 || (dx.abs() == 1 && dy <= 0) {
+                    continue;
+                }
+                let to = from + IVec2::new(dx, dy);
+                if !self.is_standable(to) {
+                    continue;
+                }
+                // up to the higher of the two rows, across, then down
+                let top = from.y.max(to.y);
+                let clear = self.is_clear(from, IVec2::new(from.x, top))
+                    && self.is_clear(IVec2::new(from.x, top), IVec2::new(to.x, top))
+                    && self.is_clear(IVec2::new(to.x, top), to);
+                if clear {
+                    push(to, Move::Jump, 3 + 2 * (dx.abs() + dy.abs()) as u32);
+                }
+            }
+        }
+        edges
+    }
+
+    /// Floor cell below the position, where a body there ends up standing.
+    fn ground(&self, position: Vec3) -> Option<usize> {
+        self.landing(Self::cell(position))
+            .and_then(|cell| self.index(cell))
+    }
+
+    /// Shortest way from the floor below `from` to the floor below `to`, not
+    /// counting the cell it starts in, or `None` when there is none.
+    pub fn find_path(&self, from: Vec3, to: Vec3) -> Option<Vec<Step>> {
+        let start = self.ground(from)?;
+        let goal = self.ground(to)?;
+        let goal_cell = self.cell_at(goal);
+        // every move costs at least 2 per column and 1 per row
+        let estimate = |index: usize| {
+            let offset = (goal_cell - self.cell_at(index)).abs();
+            (2 * offset.x + offset.y) as u32
+        };
+
+        let mut costs = HashMap::from([(start, 0)]);
+        let mut came_from: HashMap<usize, (usize, Move)> = HashMap::new();
+        let mut open = BinaryHeap::from([Reverse((estimate(start), start))]);
+        while let Some(Reverse((_, current))) = open.pop() {
+            if current == goal {
+                let mut path = vec![];
+                let mut index = goal;
+                while let Some(&(previous, movement)) = came_from.get(&index) {
+                    path.push(Step {
+                        position: Self::center(self.cell_at(index)),
+                        movement,
+                    });
+                    index = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let cost = costs[&current];
+            for edge in self.edges.get(current).into_iter().flatten() {
+                let next_cost = cost + edge.cost;
+                if costs.get(&edge.to).is_some_and(|&known| known <= next_cost) {
+                    continue;
+                }
+                costs.insert(edge.to, next_cost);
+                came_from.insert(edge.to, (current, edge.movement));
+                open.push(Reverse((next_cost + estimate(edge.to), edge.to)));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A floor along the bottom of the map with spikes `gap` cells wide in
+    /// the middle.
+    fn floor_with_gap(gap: i32) -> Navigation {
+        let width = 8 + gap;
+        let mut navigation = Navigation::new(width as u32, 4);
+        for x in 0..width {
+            let position = Navigation::center(IVec2::new(x, 0));
+            if (4..4 + gap).contains(&x) {
+                navigation.set_hazard(position);
+            } else {
+                navigation.set_solid(position);
+            }
+        }
+        navigation.connect();
+        navigation
+    }
+
+    fn across(navigation: &Navigation, gap: i32) -> Option<Vec<Step>> {
+        navigation.find_path(
+            Navigation::center(IVec2::new(3, 1)),
+            Navigation::center(IVec2::new(4 + gap, 1)),
+        )
+    }
+
+    #[test]
+    fn jumps_a_gap_in_reach() {
+        let path = across(&floor_with_gap(JUMP_DISTANCE - 1), JUMP_DISTANCE - 1).unwrap();
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].movement, Move::Jump);
+    }
+
+    #[test]
+    fn opened_doors_let_bodies_through() {
+        let mut navigation = floor_with_gap(0);
+        // a door up to the ceiling in the middle of the floor
+        let door = Navigation::center(IVec2::new(5, 2));
+        let size = Vec2::new(BLOCK_SIZE, 3.0 * BLOCK_SIZE);
+        for cell in Navigation::covered(door, size) {
+            navigation.set_solid(Navigation::center(cell));
+        }
+        navigation.connect();
+        let from = Navigation::center(IVec2::new(1, 1));
+        let to = Navigation::center(IVec2::new(7, 1));
+        assert!(navigation.find_path(from, to).is_none());
+
+        for cell in Navigation::covered(door, size) {
+            navigation.set_free(Navigation::center(cell));
+        }
+        navigation.connect();
+        let path = navigation.find_path(from, to).unwrap();
+        assert!(path.iter().all(|step| step.movement == Move::Walk));
+    }
+
+    #[test]
+    fn no_path_across_a_gap_out_of_reach() {
+        let gap = JUMP_DISTANCE;
+        assert!(across(&floor_with_gap(gap), gap).is_none());
+    }
+}
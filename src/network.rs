@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{carry::Carryable, collide::Contact, slider::Slider, state::GameState};
+
+/// Speed a box has to hit a fragile jammer with to knock it out.
+const KNOCK_OUT_SPEED: f32 = 400.0;
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkRng>()
+            .init_resource::<LevelSignal>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Play)
+                    .with_system(update_jammers.after("collision")),
+            );
+    }
+}
+
+/// Randomness for everything that goes wrong on the network. It is seeded
+/// from the level when it loads, so the same inputs always get the same
+/// treatment.
+pub struct NetworkRng(pub StdRng);
+
+impl NetworkRng {
+    pub fn seeded(seed: u64) -> Self {
+        NetworkRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for NetworkRng {
+    fn default() -> Self {
+        NetworkRng::seeded(0)
+    }
+}
+
+/// Network conditions of the whole level, read from the map properties.
+#[derive(Debug, Clone, Default)]
+pub struct LevelSignal {
+    /// Chance of losing an input frame at the weakest signal.
+    pub packet_loss: f32,
+    /// Inputs per second that get through at the weakest signal, unlimited
+    /// when `None`.
+    pub bandwidth: Option<f32>,
+    pub overflow: Overflow,
+    pub on_disconnect: Disconnect,
+}
+
+/// What the robot does while it has no signal at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Disconnect {
+    /// Self destruct, restarting the level.
+    #[default]
+    Die,
+    /// Stop and wait for the signal to come back.
+    Freeze,
+    /// Keep walking the way the last received input said.
+    Autopilot,
+}
+
+/// What happens to inputs that don't fit through the bandwidth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Wait and arrive in a later tick.
+    #[default]
+    Queue,
+    /// Get thrown away.
+    Drop,
+}
+
+/// A region of the level with worse network conditions than the rest of it.
+#[derive(Component, Debug)]
+pub struct SignalZone {
+    pub size: Vec2,
+    /// Chance of losing an input frame inside the zone, on top of the loss
+    /// from weak signal.
+    pub packet_loss: f32,
+}
+
+impl SignalZone {
+    pub fn contains(&self, center: Vec3, position: Vec3) -> bool {
+        let offset = (position - center).truncate().abs();
+        offset.x <= self.size.x / 2.0 && offset.y <= self.size.y / 2.0
+    }
+}
+
+/// Makes the signal worse for robots within `radius` of it.
+#[derive(Component, Debug, Default)]
+pub struct Jammer {
+    pub radius: f32,
+    /// Ticks of latency added on top of the distance to the tower.
+    pub latency: usize,
+    /// Chance of losing an input frame, on top of the rest.
+    pub packet_loss: f32,
+    /// Ticks of jitter added on top of the tower's.
+    pub jitter: usize,
+    /// Circuit output that switches the jammer off while active.
+    pub switch: Option<Entity>,
+    /// Whether a box knocked into it breaks it for good.
+    pub fragile: bool,
+    pub switched_off: bool,
+    pub knocked_out: bool,
+}
+
+impl Jammer {
+    pub fn jams(&self, center: Vec3, position: Vec3) -> bool {
+        !self.switched_off
+            && !self.knocked_out
+            && center.truncate().distance(position.truncate()) <= self.radius
+    }
+}
+
+fn update_jammers(
+    mut contacts: EventReader<Contact>,
+    boxes: Query<(), With<Carryable>>,
+    sliders: Query<&Slider>,
+    mut jammers: Query<(&mut Jammer, &mut Sprite)>,
+) {
+    for contact in contacts.iter() {
+        if boxes.get(contact.entity).is_err() || contact.velocity.length() < KNOCK_OUT_SPEED {
+            continue;
+        }
+        if let Ok((mut jammer, _)) = jammers.get_mut(contact.other) {
+            jammer.knocked_out |= jammer.fragile;
+        }
+    }
+    for (mut jammer, mut sprite) in jammers.iter_mut() {
+        jammer.switched_off = jammer
+            .switch
+            .and_then(|switch| sliders.get(switch).ok())
+            .is_some_and(|slider| slider.activated);
+        sprite.color = if jammer.switched_off || jammer.knocked_out {
+            Color::GRAY
+        } else {
+            Color::rgb(1.0, 0.4, 0.4)
+        };
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+
+use crate::{state::GameState, velocity::Velocity};
+
+pub struct PathPlugin;
+
+impl Plugin for PathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play).with_system(follow_paths.before("collision")),
+        );
+    }
+}
+
+/// Points something moves between at `speed`, in order and looping around.
+/// Things without a velocity are moved along it directly, the others steer
+/// by [`PathFollower::step`] themselves.
+#[derive(Component, Debug, Default)]
+pub struct PathFollower {
+    pub path: Vec<Vec3>,
+    pub next: usize,
+    pub speed: f32,
+}
+
+impl PathFollower {
+    pub fn new(path: Vec<Vec3>, speed: f32) -> Self {
+        Self {
+            path,
+            next: 0,
+            speed,
+        }
+    }
+
+    /// How far to move from `position` toward the next point over `delta`
+    /// seconds, moving on to the point after it once it is reached.
+    pub fn step(&mut self, position: Vec3, delta: f32) -> Vec3 {
+        if self.path.is_empty() {
+            return Vec3::ZERO;
+        }
+        let target = self.path[self.next].truncate();
+        let position = position.truncate();
+        let step = self.speed * delta;
+        if position.distance(target) <= step {
+            self.next = (self.next + 1) % self.path.len();
+        }
+        (target - position).clamp_length_max(step).extend(0.0)
+    }
+}
+
+fn follow_paths(
+    time: Res<Time>,
+    mut followers: Query<(&mut Transform, &mut PathFollower), Without<Velocity>>,
+) {
+    for (mut transform, mut follower) in followers.iter_mut() {
+        let step = follower.step(transform.translation, time.delta_seconds());
+        transform.translation += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn followers_loop_around_their_path() {
+        let mut follower = PathFollower::new(vec![Vec3::X * 10.0, Vec3::ZERO], 10.0);
+        let mut position = Vec3::ZERO;
+        for expected in [Vec3::X * 10.0, Vec3::ZERO, Vec3::X * 10.0] {
+            position += follower.step(position, 1.0);
+            assert_eq!(position, expected);
+        }
+    }
+}
@@ -1,11 +1,17 @@
 use std::collections::VecDeque;
 
 use bevy::prelude::*;
+use rand::Rng;
 
 use crate::{
     animation::Animation,
-    collide::{Collider, ColliderKind, CollisionFlags},
+    carry::{Carried, Carryable},
+    collide::{Collider, ColliderKind, CollisionFlags, Contact, GameEvent, Surface},
+    controls::Action,
+    drone::Drone,
+    inventory::Inventory,
     map::{CellTower, BLOCK_SIZE},
+    network::{Disconnect, Jammer, LevelSignal, NetworkRng, Overflow, SignalZone},
     state::GameState,
     tiled_loader::WorldObject,
     velocity::{Gravity, Velocity},
@@ -18,23 +24,98 @@ pub struct PlayerPlugin;
 
 pub struct Latency(pub VecDeque<i32>);
 
+/// Weight of a robot, carrying a box adds the box's weight to it.
+const WEIGHT: f32 = 5.0;
+/// Speed a thrown box leaves the robot with, both forward and up.
+const THROW_SPEED: f32 = 800.0;
+/// Fastest a robot falls while pushing against a wall.
+const WALL_SLIDE_SPEED: f32 = 300.0;
+/// Speed a wall jump pushes the robot away from the wall with.
+const WALL_JUMP_SPEED: f32 = 1200.0;
+/// Speed of climbing up and down ladders.
+const CLIMB_SPEED: f32 = 400.0;
+
+/// Latency in ticks at which the signal is at its weakest.
+pub const MAX_LATENCY: usize = 40;
+/// Latency in ticks from which the signal counts as degraded.
+pub const DEGRADED_LATENCY: usize = 25;
+
+/// State of the robot's connection to its nearest tower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Signal {
+    #[default]
+    Connected,
+    Degraded,
+    Disconnected,
+}
+
+impl Signal {
+    fn from_latency(latency: usize) -> Self {
+        match latency {
+            0..DEGRADED_LATENCY => Signal::Connected,
+            DEGRADED_LATENCY..=MAX_LATENCY => Signal::Degraded,
+            _ => Signal::Disconnected,
+        }
+    }
+}
+
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(GameState::Play).with_system(load_player_resources))
             .add_system_set(
                 SystemSet::on_update(GameState::Play)
-                    .with_system(player_inputs)
+                    .with_system(switch_robot)
+                    .with_system(player_inputs.after(switch_robot))
                     .with_system(update_player)
-                    .with_system(update_latency.after(player_inputs)), //.with_system(_print_player_inputs.after("map_update"))
+                    .with_system(update_latency.after(player_inputs))
+                    .with_system(count_frame_time), //.with_system(_print_player_inputs.after("map_update"))
             );
     }
 }
 
+/// How forgiving jumping is, set per level from the map properties.
+#[derive(Debug, Clone, Copy)]
+pub struct JumpTuning {
+    /// Seconds after walking off a ledge that a jump still works.
+    pub coyote_time: f32,
+    /// Seconds a jump that arrived in the air is kept until the robot lands.
+    pub buffer: f32,
+    /// Part of the upward speed kept when jump is let go early.
+    pub cut: f32,
+}
+
+impl Default for JumpTuning {
+    fn default() -> Self {
+        JumpTuning {
+            coyote_time: 0.1,
+            buffer: 0.15,
+            cut: 0.5,
+        }
+    }
+}
+
+/// Moves a level unlocks for its robots through the map properties.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Abilities {
+    /// Falling slowly while pushing against a wall.
+    pub wall_slide: bool,
+    /// Jumping off a wall touched in the air.
+    pub wall_jump: bool,
+    /// Climbing ladders.
+    pub climb: bool,
+}
+
+/// Tile a robot that can climb moves up and down on instead of falling.
+#[derive(Component, Debug)]
+pub struct Ladder;
+
 #[derive(Bundle)]
 pub struct PlayerBundle {
     #[bundle]
     sprite_sheet: SpriteSheetBundle,
     player: Player,
+    link: Link,
+    inventory: Inventory,
     collider: Collider,
     velocity: Velocity,
     gravity: Gravity,
@@ -58,8 +139,10 @@ impl PlayerBundle {
                 ..default()
             },
             player: Player::default(),
+            link: Link::default(),
+            inventory: Inventory::default(),
             collider: Collider {
-                kind: ColliderKind::Movable(5.0),
+                kind: ColliderKind::Movable(WEIGHT),
                 size: Vec2::new(22.0 / 32.0 * BLOCK_SIZE, BLOCK_SIZE),
                 flags: CollisionFlags::empty(),
             },
@@ -75,6 +158,16 @@ impl PlayerBundle {
             world_object: WorldObject,
         }
     }
+
+    pub fn with_jump(mut self, jump: JumpTuning) -> Self {
+        self.player.jump = jump;
+        self
+    }
+
+    pub fn with_abilities(mut self, abilities: Abilities) -> Self {
+        self.player.abilities = abilities;
+        self
+    }
 }
 
 fn load_player_resources(
@@ -91,120 +184,654 @@ fn load_player_resources(
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-enum GameInput {
+pub enum GameInput {
     Left,
     Right,
     Jump,
+    Up,
+    Down,
+    Interact,
+    /// Sent every tick jump is held, letting go early makes for a lower jump.
+    HoldJump,
+}
+
+impl GameInput {
+    /// Whether the input is sent every tick its key is held rather than once
+    /// per press.
+    fn is_held(&self) -> bool {
+        matches!(
+            self,
+            GameInput::Left
+                | GameInput::Right
+                | GameInput::Up
+                | GameInput::Down
+                | GameInput::HoldJump
+        )
+    }
 }
 
 // TODO move to another file
 
-#[derive(Component, Debug, Default)]
+#[derive(Component, Debug, Default, Clone)]
 pub struct Player {
+    /// Box the robot is carrying over its head.
+    pub holding: Option<Entity>,
+    pub jump: JumpTuning,
+    pub abilities: Abilities,
+    /// Seconds since the robot last stood on the floor.
+    airtime: f32,
+    /// Seconds left for a buffered jump to happen.
+    jump_buffer: f32,
+    /// Whether the robot is rising from a jump that can still be cut short.
+    jumping: bool,
+}
+
+/// Connection through the cell towers that carries the player's inputs to
+/// whatever they control, a robot or a hacked enemy.
+#[derive(Component, Debug, Default)]
+pub struct Link {
     // # of ticks before register TODO make it millisecionds,
     pub latency: usize,
+    /// Chance that a frame of inputs never arrives.
+    pub loss: f32,
+    /// How many ticks a frame of inputs can arrive early or late.
+    pub jitter: usize,
+    /// Inputs per second that reach the robot, unlimited when `None`.
+    pub bandwidth: Option<f32>,
+    /// Inputs that can still be delivered before the bandwidth runs out.
+    allowance: f32,
     queue: VecDeque<Vec<GameInput>>,
+    /// Inputs that arrived but didn't fit through the bandwidth yet.
+    backlog: Vec<GameInput>,
+    pub signal: Signal,
+    /// The last frame of inputs that reached the robot.
+    last_inputs: Vec<GameInput>,
 }
 
-fn player_inputs(keyboard_input: Res<Input<KeyCode>>, mut player_query: Query<&mut Player>) {
-    for mut player in player_query.iter_mut() {
-        let latency = player.latency;
+impl Link {
+    /// Inputs still on their way with the number of ticks until they arrive.
+    pub fn pending(&self) -> impl Iterator<Item = (usize, &GameInput)> {
+        self.backlog.iter().map(|input| (0, input)).chain(
+            self.queue
+                .iter()
+                .enumerate()
+                .flat_map(|(ticks, inputs)| inputs.iter().map(move |input| (ticks, input))),
+        )
+    }
+
+    /// Frames of inputs still on their way, one per tick until they arrive.
+    pub fn pending_frames(&self) -> Vec<Vec<GameInput>> {
+        let mut frames: Vec<Vec<GameInput>> = self.queue.iter().cloned().collect();
+        if !self.backlog.is_empty() {
+            if frames.is_empty() {
+                frames.push(vec![]);
+            }
+            frames[0].splice(0..0, self.backlog.iter().cloned());
+        }
+        frames
+    }
 
-        let mut inputs: Vec<GameInput> = keyboard_input
+    /// Takes the inputs that reach the robot this tick, or what it does
+    /// instead while it has no signal.
+    pub fn receive(&mut self, delta: f32, level_signal: &LevelSignal) -> Vec<GameInput> {
+        if self.signal != Signal::Disconnected {
+            let inputs = self.deliver(delta, level_signal.overflow);
+            if !inputs.is_empty() {
+                self.last_inputs = inputs.clone();
+            }
+            return inputs;
+        }
+        self.drain();
+        match level_signal.on_disconnect {
+            // jumps and interactions were single presses, only the moving carries on
+            Disconnect::Autopilot => self
+                .last_inputs
+                .iter()
+                .filter(|input| {
+                    matches!(
+                        input,
+                        GameInput::Left | GameInput::Right | GameInput::Up | GameInput::Down
+                    )
+                })
+                .cloned()
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Switches to `signal`, returning whether that just cut the connection.
+    fn set_signal(&mut self, signal: Signal) -> bool {
+        let lost = signal == Signal::Disconnected && self.signal != Signal::Disconnected;
+        self.signal = signal;
+        lost
+    }
+
+    /// Throws away the inputs still on their way.
+    pub fn drain(&mut self) {
+        self.queue.clear();
+        self.backlog.clear();
+    }
+
+    /// Whether the robot should hold still until its signal comes back.
+    pub fn is_frozen(&self, level_signal: &LevelSignal) -> bool {
+        self.signal == Signal::Disconnected && level_signal.on_disconnect == Disconnect::Freeze
+    }
+
+    /// Takes the inputs that reach the robot this tick.
+    fn deliver(&mut self, delta: f32, overflow: Overflow) -> Vec<GameInput> {
+        let mut inputs = std::mem::take(&mut self.backlog);
+        for input in self.queue.pop_front().unwrap_or_default() {
+            // a key still held since the inputs waiting from earlier ticks
+            // moves the robot once, not once per tick it was queued
+            if input.is_held() && inputs.contains(&input) {
+                continue;
+            }
+            inputs.push(input);
+        }
+        let bandwidth = match self.bandwidth {
+            Some(bandwidth) => bandwidth,
+            None => return inputs,
+        };
+        // unused bandwidth saves up for at most a second
+        self.allowance = (self.allowance + bandwidth * delta).min(bandwidth.max(1.0));
+        let allowed = self.allowance.floor() as usize;
+        if inputs.len() > allowed {
+            let mut excess = inputs.split_off(allowed);
+            if overflow == Overflow::Queue {
+                // at most a second of inputs waits, the oldest go first
+                let capacity = bandwidth.max(1.0) as usize;
+                excess.drain(..excess.len().saturating_sub(capacity));
+                self.backlog = excess;
+            }
+        }
+        self.allowance -= inputs.len() as f32;
+        inputs
+    }
+}
+
+/// Marks the robot that the player is controlling.
+#[derive(Component, Debug)]
+pub struct Active;
+
+type RobotQuery<'a> = (Entity, Option<&'a Active>, Option<&'a Drone>);
+
+/// Hands control to the next robot when switching is pressed or the active
+/// drone runs out of power, and to the first one when no robot has it. Drones
+/// out of power are skipped.
+fn switch_robot(
+    mut commands: Commands,
+    actions: Res<Input<Action>>,
+    robots: Query<RobotQuery, With<Player>>,
+    hacking: Query<(), (With<Active>, Without<Player>)>,
+) {
+    // the robots wait while one of them controls a hacked enemy
+    if !hacking.is_empty() {
+        return;
+    }
+    let mut robots: Vec<(Entity, bool, bool)> = robots
+        .iter()
+        .map(|(entity, active, drone)| {
+            let powered = drone.is_none_or(Drone::is_powered);
+            (entity, active.is_some(), powered)
+        })
+        .collect();
+    robots.sort_by_key(|(entity, ..)| entity.id());
+    let current = robots.iter().position(|(_, active, _)| *active);
+    let next = match current {
+        Some(i) if actions.just_pressed(Action::SwitchRobot) || !robots[i].2 => (1..=robots.len())
+            .map(|offset| (i + offset) % robots.len())
+            .find(|&next| robots[next].2),
+        Some(_) => return,
+        None => robots.iter().position(|(.., powered)| *powered),
+    };
+    if let Some(i) = current {
+        commands.entity(robots[i].0).remove::<Active>();
+    }
+    if let Some(next) = next {
+        commands.entity(robots[next].0).insert(Active);
+    }
+}
+
+fn player_inputs(
+    actions: Res<Input<Action>>,
+    mut rng: ResMut<NetworkRng>,
+    mut links: Query<(&mut Link, Option<&Drone>), With<Active>>,
+) {
+    for (mut link, drone) in links.iter_mut() {
+        let flying = drone.is_some();
+        let latency = link.latency;
+        // nothing gets through without signal
+        if link.signal == Signal::Disconnected {
+            continue;
+        }
+
+        let mut inputs: Vec<GameInput> = actions
             .get_pressed()
-            .filter_map(|input| match input {
-                KeyCode::W | KeyCode::Up => {
-                    if keyboard_input.just_pressed(KeyCode::W)
-                        || keyboard_input.just_pressed(KeyCode::Up)
-                    {
-                        return Some(GameInput::Jump);
-                    }
-                    None
+            .filter_map(|action| match action {
+                Action::Jump if flying => Some(GameInput::Up),
+                Action::Jump if actions.just_pressed(Action::Jump) => Some(GameInput::Jump),
+                Action::Jump => Some(GameInput::HoldJump),
+                Action::Down => Some(GameInput::Down),
+                Action::Interact if actions.just_pressed(Action::Interact) => {
+                    Some(GameInput::Interact)
                 }
-                KeyCode::A | KeyCode::Left => Some(GameInput::Left),
-                KeyCode::D | KeyCode::Right => Some(GameInput::Right),
+                Action::Left => Some(GameInput::Left),
+                Action::Right => Some(GameInput::Right),
                 _ => None,
             })
             .collect();
         if !inputs.is_empty() {
-            if player.queue.len() < latency + 1 {
-                player.queue.resize(latency + 1, vec![]);
+            // the whole frame goes out in one packet and is lost together
+            if rng.0.gen::<f32>() < link.loss {
+                continue;
+            }
+            // jitter can make a frame overtake the ones sent before it
+            let jitter = link.jitter as isize;
+            let delay = if jitter > 0 {
+                (latency as isize + rng.0.gen_range(-jitter..=jitter)).max(0) as usize
+            } else {
+                latency
+            };
+            if link.queue.len() < delay + 1 {
+                link.queue.resize(delay + 1, vec![]);
             }
 
-            inputs.append(&mut player.queue[latency]);
+            inputs.append(&mut link.queue[delay]);
             inputs.dedup();
-            player.queue[latency] = inputs;
+            link.queue[delay] = inputs;
         }
     }
 }
 
 fn _print_player_inputs(player_query: Query<&Player>) {
-    let player = player_query.get_single();
-    println!("{:?}", player);
+    for player in player_query.iter() {
+        println!("{:?}", player);
+    }
 }
 
+type PlayerQuery<'a> = (
+    Entity,
+    &'a mut Player,
+    &'a mut Link,
+    &'a mut Collider,
+    &'a mut Velocity,
+    &'a Gravity,
+    &'a mut Transform,
+    &'a mut TextureAtlasSprite,
+    &'a mut Animation,
+);
+
+type BoxQuery<'a> = (&'a Collider, &'a mut Velocity);
+
 fn update_player(
-    mut player_query: Query<(
-        &mut Player,
-        &Collider,
-        &mut Velocity,
-        &mut TextureAtlasSprite,
-        &mut Animation,
-    )>,
+    mut commands: Commands,
+    time: Res<Time>,
+    level_signal: Res<LevelSignal>,
+    mut contacts: EventReader<Contact>,
+    tiles: Query<(Option<&Surface>, Option<&Ladder>)>,
+    mut player_query: Query<PlayerQuery, Without<Drone>>,
+    mut boxes: Query<BoxQuery, (With<Carryable>, Without<Player>)>,
 ) {
-    for (mut player, collider, mut velocity, mut player_sprite, mut animation) in
-        player_query.iter_mut()
+    let contacts: Vec<&Contact> = contacts.iter().collect();
+    for (
+        entity,
+        mut player,
+        mut link,
+        mut collider,
+        mut velocity,
+        gravity,
+        mut transform,
+        mut player_sprite,
+        mut animation,
+    ) in player_query.iter_mut()
     {
-        let inputs: Vec<GameInput> = player.queue.pop_front().unwrap_or_default();
-        animation.running = false;
+        let floor = gravity.floor();
+
+        // stand the robot on whichever side is currently down
+        transform.rotation = gravity.rotation();
+        if gravity.is_sideways() != (collider.size.x > collider.size.y) {
+            collider.size = Vec2::new(collider.size.y, collider.size.x);
+            velocity.drag = Vec3::new(velocity.drag.y, velocity.drag.x, velocity.drag.z);
+        }
+        // player starts off facing right so facing left is flip_x
+        let facing_left = |direction: Vec3| (transform.rotation * Vec3::X).dot(direction) < 0.0;
+
+        // how much grip the surface under the robot gives, full grip in the air
+        let traction = contacts
+            .iter()
+            .filter(|contact| contact.entity == entity && contact.side.contains(floor))
+            .find_map(|contact| tiles.get(contact.other).ok()?.0)
+            .map(|surface| surface.friction)
+            .unwrap_or(1.0);
+
+        if link.is_frozen(&level_signal) {
+            let right = gravity.right();
+            let walking = velocity.linvel.dot(right);
+            velocity.linvel -= right * walking;
+        }
+        let inputs = link.receive(time.delta_seconds(), &level_signal);
+        let load = player
+            .holding
+            .and_then(|held| boxes.get(held).ok())
+            .map_or(0.0, |(held_collider, _)| held_collider.weight());
+        let footing = Footing {
+            on_floor: collider.flags.contains(floor),
+            ladder: contacts.iter().any(|contact| {
+                contact.entity == entity && matches!(tiles.get(contact.other), Ok((_, Some(_))))
+            }),
+            wall: [-gravity.right(), gravity.right()]
+                .into_iter()
+                .find(|side| collider.flags.contains(CollisionFlags::facing(*side))),
+            traction,
+        };
+        let walking = player.steer(
+            &inputs,
+            &mut velocity,
+            gravity,
+            footing,
+            load,
+            time.delta_seconds(),
+        );
+        animation.running = walking.is_some();
+        if let Some(direction) = walking {
+            player_sprite.flip_x = facing_left(direction);
+        }
+
+        if !inputs.contains(&GameInput::Interact) {
+            continue;
+        }
+        match player.holding.take() {
+            // walking while letting go throws the box that way
+            Some(held) => {
+                commands.entity(held).remove::<Carried>();
+                if let (Some(direction), Ok((_, mut held_velocity))) =
+                    (walking, boxes.get_mut(held))
+                {
+                    held_velocity.apply_impulse((direction - gravity.down()) * THROW_SPEED);
+                }
+            }
+            None => {
+                // pick up a box right next to the robot
+                let sides = if gravity.is_sideways() {
+                    CollisionFlags::TOP | CollisionFlags::BOTTOM
+                } else {
+                    CollisionFlags::LEFT | CollisionFlags::RIGHT
+                };
+                let height = |size: Vec2| {
+                    if gravity.is_sideways() {
+                        size.x
+                    } else {
+                        size.y
+                    }
+                };
+                player.holding = contacts
+                    .iter()
+                    .filter(|contact| contact.entity == entity && contact.side.intersects(sides))
+                    .find_map(|contact| {
+                        Some((contact.other, boxes.get(contact.other).ok()?.0.size))
+                    })
+                    .map(|(held, size)| {
+                        commands.entity(held).insert(Carried {
+                            carrier: entity,
+                            offset: -gravity.down() * (height(collider.size) + height(size)) / 2.0,
+                        });
+                        held
+                    });
+            }
+        }
+    }
+}
+
+/// What a robot touches, worked out from its contacts.
+#[derive(Debug, Clone, Copy)]
+pub struct Footing {
+    pub on_floor: bool,
+    pub ladder: bool,
+    /// Side of the robot a wall is on.
+    pub wall: Option<Vec3>,
+    /// Friction of the surface it stands on.
+    pub traction: f32,
+}
+
+impl Player {
+    /// Moves the robot for one tick of arrived inputs: walking, climbing,
+    /// wall slides and the buffered, coyote and cut short jumps. Returns the
+    /// way it walks. The ghost plays the queued inputs through this too, so
+    /// it jumps the same.
+    pub fn steer(
+        &mut self,
+        inputs: &[GameInput],
+        velocity: &mut Velocity,
+        gravity: &Gravity,
+        footing: Footing,
+        load: f32,
+        delta: f32,
+    ) -> Option<Vec3> {
+        let Footing {
+            on_floor,
+            wall,
+            traction,
+            ..
+        } = footing;
+        if on_floor {
+            self.airtime = 0.0;
+        } else {
+            self.airtime += delta;
+        }
+        let on_ladder = self.abilities.climb && footing.ladder;
+        let mut walking = None;
         for input in inputs {
             match input {
+                // jump climbs instead while on a ladder
+                GameInput::Jump if on_ladder => continue,
+                // jumps wait in the buffer so they still happen on landing
                 GameInput::Jump => {
-                    if collider.flags.contains(CollisionFlags::BOTTOM) {
-                        velocity.linvel += Vec3::Y * 2300.0;
-                    }
-                }
-                GameInput::Left => {
-                    // player starts off facing right so facing left is true
-                    // facing right is false
-                    player_sprite.flip_x = true;
-                    animation.running = true;
-                    velocity.linvel += Vec3::X * -200.0
+                    self.jump_buffer = self.jump.buffer.max(delta);
+                    continue;
                 }
-                GameInput::Right => {
-                    player_sprite.flip_x = false;
-                    animation.running = true;
-                    velocity.linvel += Vec3::X * 200.0
+                GameInput::Up | GameInput::Down | GameInput::Interact | GameInput::HoldJump => {}
+                GameInput::Left => walking = Some(-gravity.right()),
+                GameInput::Right => walking = Some(gravity.right()),
+            }
+            apply_input(input, velocity, gravity, on_floor, traction, load);
+        }
+
+        if on_ladder {
+            // the ladder holds the robot up against gravity
+            velocity.apply_force(-gravity.0);
+            let climbing: f32 = inputs
+                .iter()
+                .map(|input| match input {
+                    GameInput::Jump | GameInput::HoldJump | GameInput::Up => -1.0,
+                    GameInput::Down => 1.0,
+                    _ => 0.0,
+                })
+                .sum();
+            let down = gravity.down();
+            let falling = velocity.linvel.dot(down);
+            velocity.linvel += down * (climbing.clamp(-1.0, 1.0) * CLIMB_SPEED - falling);
+            self.jump_buffer = 0.0;
+            self.jumping = false;
+        } else if let Some(side) = wall {
+            let falling = velocity.linvel.dot(gravity.down());
+            if self.abilities.wall_slide
+                && !on_floor
+                && walking == Some(side)
+                && falling > WALL_SLIDE_SPEED
+            {
+                velocity.linvel -= gravity.down() * (falling - WALL_SLIDE_SPEED);
+            }
+        }
+
+        if self.jump_buffer > 0.0 {
+            let grounded = self.airtime <= self.jump.coyote_time;
+            let wall_jump = wall.filter(|_| !grounded && self.abilities.wall_jump);
+            if grounded || wall_jump.is_some() {
+                // every jump starts from a standstill, however long the robot
+                // has been falling off the ledge
+                let falling = velocity.linvel.dot(gravity.down()).max(0.0);
+                velocity.linvel -= gravity.down() * falling;
+                if let Some(side) = wall_jump {
+                    // kick off away from the wall
+                    velocity.linvel -= side * WALL_JUMP_SPEED;
                 }
+                apply_input(&GameInput::Jump, velocity, gravity, true, traction, load);
+                self.jump_buffer = 0.0;
+                // no second jump from the same ledge
+                self.airtime = f32::INFINITY;
+                self.jumping = true;
+            } else {
+                self.jump_buffer -= delta;
             }
         }
+        if self.jumping {
+            let rising = -velocity.linvel.dot(gravity.down());
+            let held = inputs
+                .iter()
+                .any(|input| matches!(input, GameInput::Jump | GameInput::HoldJump));
+            if rising <= 0.0 {
+                self.jumping = false;
+            } else if !held {
+                velocity.linvel += gravity.down() * rising * (1.0 - self.jump.cut);
+                self.jumping = false;
+            }
+        }
+        walking
     }
 }
 
-fn update_latency(
-    mut player_query: Query<(&Transform, &mut Player)>,
-    cell_tower_query: Query<&Transform, With<CellTower>>,
-    mut latency_counter: ResMut<Latency>,
-    time: Res<Time>,
+/// Changes the robot's velocity for one arrived input. `traction` is the
+/// friction of the surface it stands on and `load` the weight it carries.
+fn apply_input(
+    input: &GameInput,
+    velocity: &mut Velocity,
+    gravity: &Gravity,
+    on_floor: bool,
+    traction: f32,
+    load: f32,
 ) {
+    match input {
+        GameInput::Jump => {
+            if on_floor {
+                velocity.linvel -= gravity.down() * 2300.0 * WEIGHT / (WEIGHT + load);
+            }
+        }
+        GameInput::Left => velocity.linvel -= gravity.right() * 200.0 * traction,
+        GameInput::Right => velocity.linvel += gravity.right() * 200.0 * traction,
+        GameInput::Up | GameInput::Down | GameInput::Interact | GameInput::HoldJump => {}
+    }
+}
+
+fn count_frame_time(mut latency_counter: ResMut<Latency>, time: Res<Time>) {
     latency_counter
         .0
         .push_back((time.delta_seconds() * 1000.0).floor() as i32);
     while latency_counter.0.len() > 50 {
         latency_counter.0.pop_front();
     }
-    for (transform, mut player) in player_query.iter_mut() {
-        let mut shortest = f32::MAX;
-        for cell_tower_transform in cell_tower_query.iter() {
-            shortest = cell_tower_transform
-                .translation
-                .distance(transform.translation)
-                .min(shortest);
+}
+
+/// Latency in ticks and jitter of the tower nearest to `position`, `None`
+/// when the level has no towers at all.
+pub fn nearest_tower<'a>(
+    position: Vec3,
+    towers: impl Iterator<Item = (&'a Transform, &'a CellTower)>,
+) -> Option<(usize, usize)> {
+    towers
+        .map(|(transform, tower)| (transform.translation.distance(position), tower.jitter))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(distance, jitter)| ((distance / (BLOCK_SIZE / 2.0)) as usize, jitter))
+}
+
+fn update_latency(
+    mut links: Query<(&Transform, &mut Link, Option<&Player>)>,
+    cell_tower_query: Query<(&Transform, &CellTower)>,
+    signal_zones: Query<(&Transform, &SignalZone)>,
+    jammers: Query<(&Transform, &Jammer)>,
+    level_signal: Res<LevelSignal>,
+    mut events: EventWriter<GameEvent>,
+) {
+    // hacked enemies are let go instead
+    let dies = level_signal.on_disconnect == Disconnect::Die;
+    for (transform, mut link, player) in links.iter_mut() {
+        match nearest_tower(transform.translation, cell_tower_query.iter()) {
+            Some((latency, jitter)) => {
+                link.latency = latency;
+                link.jitter = jitter;
+            }
+            None => {
+                // nothing gets through without a tower, there is no lag or
+                // loss left to show
+                link.latency = 0;
+                link.jitter = 0;
+                link.loss = 0.0;
+                link.bandwidth = None;
+                if link.set_signal(Signal::Disconnected) && dies && player.is_some() {
+                    events.send(GameEvent::Death);
+                }
+                continue;
+            }
+        }
+        let mut jammer_loss = 0.0;
+        for (_, jammer) in jammers.iter().filter(|(jammer_transform, jammer)| {
+            jammer.jams(jammer_transform.translation, transform.translation)
+        }) {
+            link.latency += jammer.latency;
+            link.jitter += jammer.jitter;
+            jammer_loss += jammer.packet_loss;
+        }
+
+        let signal = Signal::from_latency(link.latency);
+        if link.set_signal(signal) && dies && player.is_some() {
+            events.send(GameEvent::Death);
+        }
+
+        // packets get lost more often the weaker the signal is
+        let weakness = (link.latency as f32 / MAX_LATENCY as f32).min(1.0);
+        let zone_loss: f32 = signal_zones
+            .iter()
+            .filter(|(zone_transform, zone)| {
+                zone.contains(zone_transform.translation, transform.translation)
+            })
+            .map(|(_, zone)| zone.packet_loss)
+            .sum();
+        link.loss = (weakness * level_signal.packet_loss + zone_loss + jammer_loss).clamp(0.0, 1.0);
+        // and fewer inputs get through, down to `bandwidth` at the weakest
+        link.bandwidth = level_signal
+            .bandwidth
+            .map(|bandwidth| bandwidth / weakness)
+            .filter(|bandwidth| bandwidth.is_finite());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(frames: Vec<GameInput>) -> Link {
+        Link {
+            bandwidth: Some(1.0),
+            queue: frames.into_iter().map(|input| vec![input]).collect(),
+            ..default()
         }
-        if shortest == f32::MAX {
-            player.latency = 0;
-            return;
+    }
+
+    #[test]
+    fn held_inputs_are_not_queued_twice() {
+        let mut link = link(vec![GameInput::Right; 4]);
+        let delivered: Vec<Vec<GameInput>> =
+            (0..4).map(|_| link.deliver(0.5, Overflow::Queue)).collect();
+        let right = vec![GameInput::Right];
+        assert_eq!(delivered, vec![vec![], right.clone(), vec![], right]);
+        assert!(link.backlog.is_empty());
+    }
+
+    #[test]
+    fn backlog_holds_at_most_a_second_of_inputs() {
+        let mut link = link(vec![GameInput::Jump; 8]);
+        for _ in 0..8 {
+            link.deliver(0.25, Overflow::Queue);
+            assert!(link.backlog.len() <= 1);
         }
-        player.latency = (shortest / (BLOCK_SIZE / 2.0)) as usize;
     }
 }
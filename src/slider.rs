@@ -21,6 +21,14 @@ pub struct Slider {
     pub activated: bool,
 }
 
+/// Whether a device switched by the circuit output `switch` is on, which is
+/// while the output is `powered`. Devices without a circuit are always on.
+pub fn is_switched_on(switch: Option<Entity>, powered: bool, sliders: &Query<&Slider>) -> bool {
+    switch
+        .and_then(|switch| sliders.get(switch).ok())
+        .is_none_or(|slider| slider.activated == powered)
+}
+
 fn update_slider_collider(mut sliders: Query<(&mut Collider, &Slider, &mut Visibility)>) {
     for (mut collider, slider, mut visibility) in sliders.iter_mut() {
         visibility.is_visible = !slider.activated;
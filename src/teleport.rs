@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    carry::{Carried, Carryable},
+    collide::Collider,
+    player::Player,
+    state::GameState,
+    velocity::Velocity,
+};
+
+/// Seconds after coming out of a teleporter before a body can be teleported
+/// again, so it doesn't bounce straight back. It also has to step out of the
+/// teleporter first.
+const COOLDOWN: f32 = 0.5;
+/// How much of a body has to be inside a teleporter along each axis for it to
+/// have entered it.
+const ENTERED: f32 = 0.9;
+
+pub struct TeleportPlugin;
+
+impl Plugin for TeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play).with_system(teleport_bodies.before("collision")),
+        );
+    }
+}
+
+/// Sends bodies that enter it to the other teleporter with the same `id`.
+#[derive(Component, Debug)]
+pub struct Teleporter {
+    pub id: String,
+    /// Radians the velocity of bodies coming out of this one is turned by.
+    pub rotation: f32,
+}
+
+/// Body that just came out of a teleporter.
+#[derive(Component, Debug)]
+pub struct Teleported {
+    cooldown: Timer,
+}
+
+/// Whether the body is far enough inside the teleporter to go through it.
+fn has_entered(position: Vec3, size: Vec2, teleporter: Vec3, teleporter_size: Vec2) -> bool {
+    let offset = (position - teleporter).truncate().abs();
+    let overlap = (size + teleporter_size) / 2.0 - offset;
+    let needed = size.min(teleporter_size) * ENTERED;
+    overlap.x >= needed.x && overlap.y >= needed.y
+}
+
+/// Robots and boxes, carried boxes go wherever their carrier goes.
+type Traveller = (Or<(With<Player>, With<Carryable>)>, Without<Teleporter>);
+
+type BodyQuery<'a> = (
+    Entity,
+    &'a mut Transform,
+    &'a Collider,
+    &'a mut Velocity,
+    Option<&'a Carried>,
+    Option<&'a mut Teleported>,
+);
+
+/// Runs before the physics step so it starts from where bodies came out, and
+/// the contacts and velocities it works out don't span the jump.
+fn teleport_bodies(
+    mut commands: Commands,
+    time: Res<Time>,
+    teleporters: Query<(Entity, &Transform, &Collider, &Teleporter)>,
+    mut bodies: Query<BodyQuery, Traveller>,
+) {
+    let mut moved: HashMap<Entity, Vec3> = HashMap::new();
+    for (entity, mut transform, collider, mut velocity, carried, teleported) in bodies.iter_mut() {
+        let position = transform.translation;
+        let entry = teleporters
+            .iter()
+            .find(|(_, teleporter, teleporter_collider, _)| {
+                has_entered(
+                    position,
+                    collider.size,
+                    teleporter.translation,
+                    teleporter_collider.size,
+                )
+            });
+        if let Some(mut teleported) = teleported {
+            teleported.cooldown.tick(time.delta());
+            if teleported.cooldown.finished() && entry.is_none() {
+                commands.entity(entity).remove::<Teleported>();
+            }
+            continue;
+        }
+        let (entry, entry_transform, _, entry_teleporter) = match entry {
+            Some(entry) if carried.is_none() => entry,
+            _ => continue,
+        };
+        let exit = teleporters.iter().find(|(other, _, _, teleporter)| {
+            *other != entry && teleporter.id == entry_teleporter.id
+        });
+        let (_, exit_transform, _, exit_teleporter) = match exit {
+            Some(exit) => exit,
+            None => continue,
+        };
+
+        let turn = Quat::from_rotation_z(exit_teleporter.rotation);
+        let offset = turn
+            * (position - entry_transform.translation)
+                .truncate()
+                .extend(0.0);
+        transform.translation = (exit_transform.translation + offset)
+            .truncate()
+            .extend(position.z);
+        velocity.linvel = turn * velocity.linvel;
+        moved.insert(entity, transform.translation);
+        commands.entity(entity).insert(Teleported {
+            cooldown: Timer::from_seconds(COOLDOWN, false),
+        });
+    }
+
+    for (_, mut transform, _, _, carried, _) in bodies.iter_mut() {
+        let carried = match carried {
+            Some(carried) => carried,
+            None => continue,
+        };
+        if let Some(&carrier) = moved.get(&carried.carrier) {
+            transform.translation = (carrier + carried.offset)
+                .truncate()
+                .extend(transform.translation.z);
+        }
+    }
+}
@@ -11,14 +11,25 @@ use bevy::{
 use bevy_ecs_tilemap::prelude::*;
 
 use crate::{
+    breakable::{Breakable, Crumbling, TileRef},
     camera::CameraAnchor,
-    collide::{Collider, ColliderKind, CollisionFlags},
+    carry::Carryable,
+    collide::{Collider, ColliderKind, CollisionFlags, Surface},
+    drone::DroneBundle,
+    enemy::EnemyBundle,
+    hazard::{Laser, LaserBeam, Saw},
+    inventory::{Key, Lock},
     map::{CellTower, ExitDoor, BLOCK_SIZE},
-    player::{PlayerBundle, PlayerTexture},
+    navigation::Navigation,
+    network::{Disconnect, Jammer, LevelSignal, NetworkRng, Overflow, SignalZone},
+    path::PathFollower,
+    player::{Abilities, JumpTuning, Ladder, PlayerBundle, PlayerTexture},
     slider::Slider,
     state::GameState,
+    teleport::Teleporter,
     trigger::{self, Button, DoorRes},
-    velocity::{Gravity, Velocity},
+    turret::Turret,
+    velocity::{Gravity, GravitySwitch, GravityZone, Springboard, Velocity, GRAVITY},
     Level,
 };
 
@@ -47,12 +58,17 @@ fn load_tile_textures(asset_server: Res<AssetServer>, mut commands: Commands) {
         let texture = asset_server.load("door.png");
         commands.insert_resource(DoorTexture(texture.clone()));
     }
+    {
+        let texture = asset_server.load("cell_tower.png");
+        commands.insert_resource(TowerTexture(texture.clone()));
+    }
 }
 #[derive(Component)]
 pub struct WorldObject;
 
 pub struct BoxTexture(pub Handle<Image>);
 pub struct DoorTexture(pub Handle<Image>);
+pub struct TowerTexture(pub Handle<Image>);
 
 #[derive(Bundle)]
 pub struct BoxBundle {
@@ -61,6 +77,7 @@ pub struct BoxBundle {
     collider: Collider,
     velocity: Velocity,
     gravity: Gravity,
+    carryable: Carryable,
     world_object: WorldObject,
 }
 impl BoxBundle {
@@ -86,9 +103,16 @@ impl BoxBundle {
                 drag: Vec3::splat(10.0),
                 ..default()
             },
+            carryable: Carryable,
             world_object: WorldObject,
         }
     }
+
+    fn with_size(mut self, size: Vec2) -> Self {
+        self.sprite_bundle.sprite.custom_size = Some(size);
+        self.collider.size = size;
+        self
+    }
 }
 
 #[derive(TypeUuid)]
@@ -159,7 +183,7 @@ pub fn process_loaded_tile_maps(
     level: Res<Level>,
     box_texture: Res<BoxTexture>,
     door_texture: Res<DoorTexture>,
-
+    tower_texture: Res<TowerTexture>,
 ) {
     let mut changed_maps = Vec::<Handle<TiledMap>>::default();
     for event in map_events.iter() {
@@ -224,10 +248,58 @@ pub fn process_loaded_tile_maps(
                     }
                     map.remove_layer(&mut commands, layer_id);
                 }
+                // circuits from the previous level point at despawned entities
+                door_res.0.clear();
+                // network effects play out the same way every time the level is played
+                let properties = &tiled_map.map.properties;
+                let seed = float_property(properties, "seed").map_or(level.0 as u64, |s| s as u64);
+                commands.insert_resource(NetworkRng::seeded(seed));
+                commands.insert_resource(LevelSignal {
+                    packet_loss: float_property(properties, "packet_loss").unwrap_or(0.0),
+                    bandwidth: float_property(properties, "bandwidth"),
+                    overflow: match string_property(properties, "bandwidth_overflow") {
+                        Some("drop") => Overflow::Drop,
+                        _ => Overflow::Queue,
+                    },
+                    on_disconnect: match string_property(properties, "on_disconnect") {
+                        Some("freeze") => Disconnect::Freeze,
+                        Some("autopilot") => Disconnect::Autopilot,
+                        _ => Disconnect::Die,
+                    },
+                });
+                let tower_jitter = float_property(properties, "jitter").unwrap_or(0.0) as usize;
+                let default_jump = JumpTuning::default();
+                let jump = JumpTuning {
+                    coyote_time: float_property(properties, "coyote_time")
+                        .unwrap_or(default_jump.coyote_time),
+                    buffer: float_property(properties, "jump_buffer")
+                        .unwrap_or(default_jump.buffer),
+                    cut: float_property(properties, "jump_cut").unwrap_or(default_jump.cut),
+                };
+                let abilities = Abilities {
+                    wall_slide: bool_property(properties, "wall_slide").unwrap_or(false),
+                    wall_jump: bool_property(properties, "wall_jump").unwrap_or(false),
+                    climb: bool_property(properties, "climb").unwrap_or(false),
+                };
                 let mut first_gid = 1;
                 for (tileset_index, tileset) in tiled_map.map.tilesets().iter().enumerate() {
                     // Once materials have been created/added we need to then create the layers.
                     for (layer_index, layer) in tiled_map.map.layers().enumerate() {
+                        if let tiled::LayerType::ObjectLayer(object_layer) = layer.layer_type() {
+                            // objects don't belong to a tileset, only spawn them once
+                            if tileset_index == 0 {
+                                spawn_objects(
+                                    &mut commands,
+                                    &tiled_map.map,
+                                    &object_layer,
+                                    &mut door_res,
+                                    &tower_texture.0,
+                                    &player_texture_res.0,
+                                    &box_texture.0,
+                                );
+                            }
+                            continue;
+                        }
                         let tile_width = tileset.tile_width as f32;
                         let tile_height = tileset.tile_height as f32;
 
@@ -268,6 +340,7 @@ pub fn process_loaded_tile_maps(
 
                         // let mut debug_boxes: Vec<SpriteBundle> = vec![];
                         let mut colliders: Vec<(Collider, Transform)> = vec![];
+                        let mut property_tiles: Vec<PropertyTile> = vec![];
                         let mut players: Vec<PlayerBundle> = vec![];
                         let mut cell_towers: Vec<Transform> = vec![];
                         let mut camera_anchors: Vec<Transform> = vec![];
@@ -284,6 +357,7 @@ pub fn process_loaded_tile_maps(
                             0u16,
                             layer_index as u16,
                             |mut tile_pos| {
+                                let layer_pos = tile_pos;
                                 if tile_pos.0 >= tiled_map.map.width
                                     || tile_pos.1 >= tiled_map.map.height
                                 {
@@ -321,10 +395,14 @@ pub fn process_loaded_tile_maps(
                                             if layer.name != "Background" {
                                                 match gid {
                                                     25 => cell_towers.push(default_transform),
-                                                    27 => players.push(PlayerBundle::new(
-                                                        default_transform,
-                                                        player_texture_res.0.clone_weak(),
-                                                    )),
+                                                    27 => players.push(
+                                                        PlayerBundle::new(
+                                                            default_transform,
+                                                            player_texture_res.0.clone_weak(),
+                                                        )
+                                                        .with_jump(jump)
+                                                        .with_abilities(abilities),
+                                                    ),
                                                     31 => camera_anchors.push(default_transform),
                                                     32 => boxes.push(BoxBundle::new(
                                                         default_transform,
@@ -366,16 +444,34 @@ pub fn process_loaded_tile_maps(
                                                         default_transform,
                                                     )),
                                                     36 => exits.push(default_transform),
-                                                    _ => colliders.push((
-                                                        Collider {
+                                                    _ => {
+                                                        let collider = Collider {
                                                             size: Vec2::new(BLOCK_SIZE, BLOCK_SIZE),
                                                             kind: ColliderKind::Movable(
                                                                 f32::INFINITY,
                                                             ),
                                                             flags: CollisionFlags::empty(),
-                                                        },
-                                                        default_transform,
-                                                    )),
+                                                        };
+                                                        match tile.get_tile() {
+                                                            Some(data)
+                                                                if !data.properties.is_empty() =>
+                                                            {
+                                                                property_tiles.push(PropertyTile {
+                                                                    collider,
+                                                                    transform: default_transform,
+                                                                    properties: data
+                                                                        .properties
+                                                                        .clone(),
+                                                                    flip_h: tile.flip_h,
+                                                                    tile_pos: layer_pos,
+                                                                })
+                                                            }
+                                                            _ => colliders.push((
+                                                                collider,
+                                                                default_transform,
+                                                            )),
+                                                        }
+                                                    }
                                                 };
                                             }
 
@@ -406,9 +502,15 @@ pub fn process_loaded_tile_maps(
                         // commands.spawn_batch(debug_boxes);
                         commands
                             .spawn_batch(colliders.into_iter().map(|(v, w)| (WorldObject, v, w)));
-                        commands.spawn_batch(
-                            cell_towers.into_iter().map(|v| (WorldObject, CellTower, v)),
-                        );
+                        for tile in property_tiles {
+                            spawn_property_tile(&mut commands, tile, layer_entity);
+                        }
+                        commands.spawn_batch(cell_towers.into_iter().map(move |v| {
+                            let tower = CellTower {
+                                jitter: tower_jitter,
+                            };
+                            (WorldObject, tower, v)
+                        }));
                         commands.spawn_batch(players);
                         commands.spawn_batch(
                             camera_anchors
@@ -448,11 +550,20 @@ pub fn process_loaded_tile_maps(
                                 .insert(collider)
                                 .insert(slider)
                                 .insert(WorldObject);
-                            door_res.0.insert(String::from("door1"), (0, entity));
+                            door_res
+                                .0
+                                .entry(String::from("door1"))
+                                .or_insert((0, vec![]))
+                                .1
+                                .push(entity);
                         }
                         for (collider, button, transform) in buttons {
                             let entity = commands.spawn().id();
-                            door_res.0.get_mut(&button.door).unwrap().0 += 1;
+                            door_res
+                                .0
+                                .entry(button.door.clone())
+                                .or_insert((0, vec![]))
+                                .0 += 1;
                             commands
                                 .entity(entity)
                                 .insert(transform)
@@ -463,11 +574,562 @@ pub fn process_loaded_tile_maps(
                     }
                     first_gid += tileset.tilecount;
                 }
+                commands.insert_resource(build_navigation(&tiled_map.map));
+            }
+        }
+    }
+}
+
+/// A static tile whose Tiled properties give it extra behaviour.
+struct PropertyTile {
+    collider: Collider,
+    transform: Transform,
+    properties: tiled::Properties,
+    flip_h: bool,
+    tile_pos: TilePos,
+}
+
+fn spawn_property_tile(commands: &mut Commands, tile: PropertyTile, layer: Entity) {
+    let mut entity = commands.spawn();
+    let mut collider = tile.collider;
+    if is_sensor_tile(&tile.properties) {
+        collider.kind = ColliderKind::Sensor;
+    }
+    if bool_property(&tile.properties, "ladder").unwrap_or(false) {
+        entity.insert(Ladder);
+    }
+    // tiles with the same `teleporter` id are linked, bodies come out of one
+    // with their velocity turned by its `teleport_rotation` in degrees
+    let teleporter = match tile.properties.get("teleporter") {
+        Some(tiled::PropertyValue::StringValue(id)) => Some(id.clone()),
+        Some(tiled::PropertyValue::IntValue(id)) => Some(id.to_string()),
+        _ => None,
+    };
+    if let Some(id) = teleporter {
+        let rotation = float_property(&tile.properties, "teleport_rotation").unwrap_or(0.0);
+        entity.insert(Teleporter {
+            id,
+            rotation: rotation.to_radians(),
+        });
+    }
+    entity
+        .insert(collider)
+        .insert(tile.transform)
+        .insert(WorldObject);
+    if let Some(mut surface) = surface_properties(&tile.properties) {
+        // mirrored conveyors run the other way
+        if tile.flip_h {
+            surface.velocity *= -1.0;
+        }
+        entity.insert(surface);
+    }
+    // `bounce` is the launch speed in tiles per second
+    if let Some(bounce) = float_property(&tile.properties, "bounce") {
+        entity.insert(Springboard {
+            impulse: bounce * BLOCK_SIZE,
+        });
+    }
+    // `crumble` is the delay in seconds before the tile falls away once stood on
+    if let Some(delay) = float_property(&tile.properties, "crumble") {
+        entity.insert(Crumbling {
+            timer: Timer::from_seconds(delay, false),
+            triggered: false,
+        });
+    }
+    // `break_speed` is the impact speed in tiles per second a body needs to
+    // chip away at the tile, `durability` is how many such hits it takes, at
+    // least one, and `break_weight` how heavy the body has to be, a box by
+    // default
+    if let Some(speed) = float_property(&tile.properties, "break_speed") {
+        let durability = float_property(&tile.properties, "durability").unwrap_or(1.0);
+        entity.insert(Breakable {
+            durability: durability.max(1.0) as u32,
+            impact_speed: speed * BLOCK_SIZE,
+            weight: float_property(&tile.properties, "break_weight").unwrap_or(1.0),
+        });
+    }
+    entity.insert(TileRef {
+        layer,
+        pos: tile.tile_pos,
+    });
+}
+
+/// Walkability graph of the static tiles of the map, which tiles are solid
+/// or hazards worked out the same way as when they are spawned.
+fn build_navigation(map: &tiled::Map) -> Navigation {
+    let mut navigation = Navigation::new(map.width, map.height);
+    let mut first_gids = vec![];
+    let mut first_gid = 1;
+    for tileset in map.tilesets() {
+        first_gids.push(first_gid);
+        first_gid += tileset.tilecount;
+    }
+    for layer in map.layers().filter(|layer| layer.name != "Background") {
+        let tile_layer = match layer.layer_type() {
+            tiled::LayerType::TileLayer(tile_layer) => tile_layer,
+            // locked doors stay shut until they are opened
+            tiled::LayerType::ObjectLayer(object_layer) => {
+                for object in object_layer.objects() {
+                    if object.obj_type == "lock" {
+                        let (transform, size) = object_bounds(map, &object);
+                        for cell in Navigation::covered(transform.translation, size) {
+                            navigation.set_solid(Navigation::center(cell));
+                        }
+                    }
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        for x in 0..map.width as i32 {
+            for y in 0..map.height as i32 {
+                let tile = match tile_layer.get_tile(x, y) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                // rows count up from the bottom of the map
+                let cell = Vec2::new(x as f32, (map.height as i32 - 1 - y) as f32);
+                let position = ((cell + 0.5) * BLOCK_SIZE).extend(0.0);
+                match first_gids[tile.tileset_index()] + tile.id() {
+                    35 => navigation.set_hazard(position),
+                    // robots, boxes, doors and markers come and go or aren't there
+                    10 | 25..=27 | 31..=34 | 36 => (),
+                    _ => {
+                        let sensor = tile
+                            .get_tile()
+                            .is_some_and(|data| is_sensor_tile(&data.properties));
+                        if !sensor {
+                            navigation.set_solid(position);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    navigation.connect();
+    navigation
+}
+
+/// Whether bodies pass through the tile rather than stand on it, like ladders
+/// they climb and teleporters they walk into.
+fn is_sensor_tile(properties: &tiled::Properties) -> bool {
+    bool_property(properties, "ladder").unwrap_or(false) || properties.contains_key("teleporter")
+}
+
+/// World transform of the center of an object and its size.
+fn object_bounds(map: &tiled::Map, object: &tiled::Object) -> (Transform, Vec2) {
+    let scale = BLOCK_SIZE / map.tile_width as f32;
+    let (width, height) = match object.shape {
+        tiled::ObjectShape::Rect { width, height } => (width, height),
+        tiled::ObjectShape::Ellipse { width, height } => (width, height),
+        _ => (0.0, 0.0),
+    };
+    let center = Vec3::new(
+        (object.x + width / 2.0) * scale,
+        map.height as f32 * BLOCK_SIZE - (object.y + height / 2.0) * scale,
+        1.0,
+    );
+    (
+        Transform::from_translation(center),
+        Vec2::new(width, height) * scale,
+    )
+}
+
+/// World positions of the points of a polyline or polygon object.
+fn object_path(map: &tiled::Map, object: &tiled::Object) -> Vec<Vec3> {
+    let scale = BLOCK_SIZE / map.tile_width as f32;
+    let points = match &object.shape {
+        tiled::ObjectShape::Polyline { points } | tiled::ObjectShape::Polygon { points } => points,
+        _ => return vec![],
+    };
+    points
+        .iter()
+        .map(|(x, y)| {
+            Vec3::new(
+                (object.x + x) * scale,
+                map.height as f32 * BLOCK_SIZE - (object.y + y) * scale,
+                1.0,
+            )
+        })
+        .collect()
+}
+
+/// Spawns a circuit output that objects can read to be switched by the
+/// buttons of `circuit`.
+fn spawn_circuit_switch(commands: &mut Commands, door_res: &mut DoorRes, circuit: &str) -> Entity {
+    let entity = commands
+        .spawn()
+        .insert(Slider::default())
+        .insert(WorldObject)
+        .id();
+    door_res
+        .0
+        .entry(String::from(circuit))
+        .or_insert((0, vec![]))
+        .1
+        .push(entity);
+    entity
+}
+
+fn spawn_objects(
+    commands: &mut Commands,
+    map: &tiled::Map,
+    object_layer: &tiled::ObjectLayer,
+    door_res: &mut DoorRes,
+    tower_texture: &Handle<Image>,
+    player_texture: &Handle<TextureAtlas>,
+    box_texture: &Handle<Image>,
+) {
+    for object in object_layer.objects() {
+        let (transform, size) = object_bounds(map, &object);
+        match object.obj_type.as_str() {
+            // `direction` is up, down, left or right and `strength` is relative
+            // to normal gravity
+            "gravity" => {
+                let direction = direction_property(&object.properties).unwrap_or(-Vec3::Y);
+                let strength = float_property(&object.properties, "strength").unwrap_or(1.0);
+                commands
+                    .spawn()
+                    .insert(transform)
+                    .insert(GravityZone {
+                        size,
+                        gravity: direction * GRAVITY * strength,
+                    })
+                    .insert(WorldObject);
+            }
+            "gravity_switch" => {
+                let circuit = string_property(&object.properties, "circuit").unwrap_or("door1");
+                let entity = commands
+                    .spawn()
+                    .insert(transform)
+                    .insert(Slider::default())
+                    .insert(GravitySwitch)
+                    .insert(WorldObject)
+                    .id();
+                door_res
+                    .0
+                    .entry(String::from(circuit))
+                    .or_insert((0, vec![]))
+                    .1
+                    .push(entity);
+            }
+            // `packet_loss` is the chance of losing an input frame inside the region
+            "signal" => {
+                commands
+                    .spawn()
+                    .insert(transform)
+                    .insert(SignalZone {
+                        size,
+                        packet_loss: float_property(&object.properties, "packet_loss")
+                            .unwrap_or(0.0),
+                    })
+                    .insert(WorldObject);
+            }
+            // towers placed as objects can have their own `jitter` in ticks
+            "cell_tower" => {
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        texture: tower_texture.clone_weak(),
+                        sprite: Sprite {
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    })
+                    .insert(CellTower {
+                        jitter: float_property(&object.properties, "jitter").unwrap_or(0.0)
+                            as usize,
+                    })
+                    .insert(WorldObject);
+            }
+            // `radius` is in tiles, `latency` and `jitter` in ticks, a `circuit`
+            // switches it off and `fragile` ones break when hit by a box. Drawn
+            // as a polyline it moves along it at `speed` tiles per second.
+            "jammer" => {
+                let properties = &object.properties;
+                let switch = string_property(properties, "circuit")
+                    .map(|circuit| spawn_circuit_switch(commands, door_res, circuit));
+                let path = object_path(map, &object);
+                let size = Vec2::splat(BLOCK_SIZE / 2.0);
+                let mut jammer = commands.spawn_bundle(SpriteBundle {
+                    texture: tower_texture.clone_weak(),
+                    sprite: Sprite {
+                        custom_size: Some(size),
+                        color: Color::rgb(1.0, 0.4, 0.4),
+                        ..default()
+                    },
+                    transform: path
+                        .first()
+                        .map_or(transform, |&start| Transform::from_translation(start)),
+                    ..default()
+                });
+                jammer
+                    .insert(Jammer {
+                        radius: float_property(properties, "radius").unwrap_or(3.0) * BLOCK_SIZE,
+                        latency: float_property(properties, "latency").unwrap_or(0.0) as usize,
+                        packet_loss: float_property(properties, "packet_loss").unwrap_or(0.0),
+                        jitter: float_property(properties, "jitter").unwrap_or(0.0) as usize,
+                        switch,
+                        fragile: bool_property(properties, "fragile").unwrap_or(false),
+                        ..default()
+                    })
+                    .insert(Collider {
+                        kind: ColliderKind::Sensor,
+                        size,
+                        flags: CollisionFlags::empty(),
+                    })
+                    .insert(WorldObject);
+                if !path.is_empty() {
+                    let speed = float_property(properties, "speed").unwrap_or(2.0) * BLOCK_SIZE;
+                    jammer.insert(PathFollower::new(path, speed));
+                }
+            }
+            // fires every `interval` seconds toward `direction` or, with a
+            // `sight` range in tiles, at robots it sees. `speed` of the shots
+            // is in tiles per second. A `circuit` switches it off, or on when
+            // it is `powered`.
+            "turret" => {
+                let properties = &object.properties;
+                let switch = string_property(properties, "circuit")
+                    .map(|circuit| spawn_circuit_switch(commands, door_res, circuit));
+                let interval = float_property(properties, "interval").unwrap_or(1.5);
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::MAROON,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    })
+                    .insert(Turret {
+                        direction: direction_property(properties).unwrap_or(-Vec3::X),
+                        interval: Timer::from_seconds(interval, true),
+                        sight: float_property(properties, "sight").map(|sight| sight * BLOCK_SIZE),
+                        speed: float_property(properties, "speed").unwrap_or(8.0) * BLOCK_SIZE,
+                        switch,
+                        powered: bool_property(properties, "powered").unwrap_or(false),
+                    })
+                    .insert(Collider {
+                        kind: ColliderKind::Movable(f32::INFINITY),
+                        size,
+                        flags: CollisionFlags::empty(),
+                    })
+                    .insert(WorldObject);
+            }
+            // shoots a beam toward `direction` up to `range` tiles. A `circuit`
+            // switches it off, or on when it is `powered`, and with `on_time`
+            // and `off_time` in seconds it blinks
+            "laser" => {
+                let properties = &object.properties;
+                let switch = string_property(properties, "circuit")
+                    .map(|circuit| spawn_circuit_switch(commands, door_res, circuit));
+                let blink = float_property(properties, "on_time")
+                    .zip(float_property(properties, "off_time"));
+                let beam = commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(1.0, 0.1, 0.1, 0.8),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    })
+                    .insert(LaserBeam)
+                    .insert(Collider {
+                        kind: ColliderKind::Sensor,
+                        size: Vec2::ZERO,
+                        flags: CollisionFlags::empty(),
+                    })
+                    .insert(WorldObject)
+                    .id();
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::DARK_GRAY,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    })
+                    .insert(Laser {
+                        direction: direction_property(properties).unwrap_or(-Vec3::Y),
+                        range: float_property(properties, "range").unwrap_or(10.0) * BLOCK_SIZE,
+                        switch,
+                        powered: bool_property(properties, "powered").unwrap_or(false),
+                        blink,
+                        clock: 0.0,
+                        beam,
+                    })
+                    .insert(Collider {
+                        kind: ColliderKind::Movable(f32::INFINITY),
+                        size,
+                        flags: CollisionFlags::empty(),
+                    })
+                    .insert(WorldObject);
+            }
+            // saws drawn as a polyline follow it at `speed` tiles per second,
+            // others spin in place
+            "saw" => {
+                let path = object_path(map, &object);
+                let size = Vec2::splat(0.8 * BLOCK_SIZE);
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::SILVER,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform: path
+                            .first()
+                            .map_or(transform, |&start| Transform::from_translation(start)),
+                        ..default()
+                    })
+                    .insert(Saw)
+                    .insert(PathFollower::new(
+                        path,
+                        float_property(&object.properties, "speed").unwrap_or(2.0) * BLOCK_SIZE,
+                    ))
+                    .insert(Collider {
+                        kind: ColliderKind::Death,
+                        size,
+                        flags: CollisionFlags::empty(),
+                    })
+                    .insert(WorldObject);
             }
+            // `battery` is how many seconds the drone can fly for
+            "drone" => {
+                let battery = float_property(&object.properties, "battery").unwrap_or(30.0);
+                commands.spawn_bundle(DroneBundle::new(
+                    transform,
+                    player_texture.clone_weak(),
+                    battery,
+                ));
+            }
+            // enemies drawn as a polyline fly along it, others walk between
+            // walls, `speed` is in tiles per second
+            "enemy" => {
+                let speed = float_property(&object.properties, "speed").unwrap_or(2.0) * BLOCK_SIZE;
+                let path = object_path(map, &object);
+                match path.first() {
+                    Some(&start) => {
+                        commands.spawn_bundle(
+                            EnemyBundle::new(Transform::from_translation(start), speed)
+                                .with_path(path),
+                        );
+                    }
+                    None => {
+                        // `chase` walkers find their way to robots rather than patrol
+                        let chase = bool_property(&object.properties, "chase").unwrap_or(false);
+                        commands
+                            .spawn_bundle(EnemyBundle::new(transform, speed).with_chase(chase))
+                            .insert(Gravity::default());
+                    }
+                }
+            }
+            // robots pick up keys and open locks with the same `key` id
+            "key" => {
+                let id = string_property(&object.properties, "key").unwrap_or("key");
+                let size = Vec2::splat(BLOCK_SIZE / 2.0);
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::GOLD,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    })
+                    .insert(Key { id: id.to_string() })
+                    .insert(Collider {
+                        kind: ColliderKind::Sensor,
+                        size,
+                        flags: CollisionFlags::empty(),
+                    })
+                    .insert(WorldObject);
+            }
+            "lock" => {
+                let id = string_property(&object.properties, "key").unwrap_or("key");
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgb(0.6, 0.5, 0.1),
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    })
+                    .insert(Lock { id: id.to_string() })
+                    .insert(Collider {
+                        kind: ColliderKind::Movable(f32::INFINITY),
+                        size,
+                        flags: CollisionFlags::empty(),
+                    })
+                    .insert(WorldObject);
+            }
+            "small_box" => {
+                commands.spawn_bundle(
+                    BoxBundle::new(transform, box_texture.clone_weak())
+                        .with_size(Vec2::splat(BLOCK_SIZE / 2.0)),
+                );
+            }
+            _ => (),
         }
     }
 }
 
+fn string_property<'a>(properties: &'a tiled::Properties, name: &str) -> Option<&'a str> {
+    match properties.get(name)? {
+        tiled::PropertyValue::StringValue(value) => Some(value.as_str()),
+        _ => None,
+    }
+}
+
+/// Reads a `direction` property of up, down, left or right.
+fn direction_property(properties: &tiled::Properties) -> Option<Vec3> {
+    match string_property(properties, "direction")? {
+        "up" => Some(Vec3::Y),
+        "down" => Some(-Vec3::Y),
+        "left" => Some(-Vec3::X),
+        "right" => Some(Vec3::X),
+        _ => None,
+    }
+}
+
+fn bool_property(properties: &tiled::Properties, name: &str) -> Option<bool> {
+    match properties.get(name)? {
+        tiled::PropertyValue::BoolValue(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn float_property(properties: &tiled::Properties, name: &str) -> Option<f32> {
+    match properties.get(name)? {
+        tiled::PropertyValue::FloatValue(value) => Some(*value),
+        tiled::PropertyValue::IntValue(value) => Some(*value as f32),
+        _ => None,
+    }
+}
+
+/// Reads the material of a tile from its `friction` and `surface_velocity`
+/// (in tiles per second) properties.
+fn surface_properties(properties: &tiled::Properties) -> Option<Surface> {
+    let friction = float_property(properties, "friction");
+    let velocity = float_property(properties, "surface_velocity");
+    if friction.is_none() && velocity.is_none() {
+        return None;
+    }
+    Some(Surface {
+        friction: friction.unwrap_or(1.0),
+        velocity: velocity.unwrap_or(0.0) * BLOCK_SIZE,
+    })
+}
+
 pub fn set_texture_filters_to_nearest(
     mut texture_events: EventReader<AssetEvent<Image>>,
     mut textures: ResMut<Assets<Image>>,
@@ -486,3 +1148,60 @@ pub fn set_texture_filters_to_nearest(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::{Move, Step};
+
+    fn navigation(level: &str) -> Navigation {
+        let map = tiled::Loader::new()
+            .load_tmx_map(format!("assets/levels/{}.tmx", level))
+            .unwrap();
+        build_navigation(&map)
+    }
+
+    fn path(navigation: &Navigation, from: (i32, i32), to: (i32, i32)) -> Option<Vec<Step>> {
+        navigation.find_path(
+            Navigation::center(IVec2::new(from.0, from.1)),
+            Navigation::center(IVec2::new(to.0, to.1)),
+        )
+    }
+
+    fn moves(path: &[Step]) -> Vec<Move> {
+        path.iter().map(|step| step.movement).collect()
+    }
+
+    #[test]
+    fn walks_along_the_floor() {
+        let path = path(&navigation("0"), (3, 3), (12, 3)).unwrap();
+        assert_eq!(moves(&path), vec![Move::Walk; 9]);
+        assert_eq!(Navigation::cell(path[8].position), IVec2::new(12, 3));
+    }
+
+    #[test]
+    fn jumps_over_spikes() {
+        let navigation = navigation("2");
+        assert!(!navigation.is_standable(IVec2::new(10, 2)));
+        let path = path(&navigation, (9, 3), (12, 3)).unwrap();
+        assert_eq!(moves(&path), vec![Move::Jump]);
+    }
+
+    #[test]
+    fn jumps_up_and_falls_down_a_ledge() {
+        let navigation = navigation("4");
+        let up = path(&navigation, (5, 3), (20, 6)).unwrap();
+        assert!(moves(&up).contains(&Move::Jump));
+        assert!(!moves(&up).contains(&Move::Fall));
+        let down = path(&navigation, (20, 6), (5, 3)).unwrap();
+        assert!(moves(&down).contains(&Move::Fall));
+        assert!(!moves(&down).contains(&Move::Jump));
+    }
+
+    #[test]
+    fn no_path_to_a_walled_off_floor() {
+        let navigation = navigation("0");
+        assert!(navigation.is_standable(IVec2::new(5, 1)));
+        assert!(path(&navigation, (5, 3), (5, 1)).is_none());
+    }
+}
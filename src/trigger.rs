@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use bevy::prelude::*;
 
-pub struct DoorRes(pub HashMap<String, (usize, Entity)>);
+/// Circuits by id: how many of their buttons are still unpressed, and the
+/// outputs (doors, switches) that activate once all of them are pressed.
+pub struct DoorRes(pub HashMap<String, (usize, Vec<Entity>)>);
 
 #[derive(Component)]
 pub struct Button {
@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    collide::{Collider, ColliderKind, CollisionFlags, Contact, GameEvent},
+    map::BLOCK_SIZE,
+    player::{Active, Player},
+    slider::{is_switched_on, Slider},
+    state::GameState,
+    tiled_loader::WorldObject,
+    velocity::Velocity,
+};
+
+/// Seconds a projectile flies for before it fizzles out.
+const PROJECTILE_LIFETIME: f32 = 5.0;
+/// Distance between the points checked along a line of sight.
+const SIGHT_STEP: f32 = BLOCK_SIZE / 4.0;
+
+pub struct TurretPlugin;
+
+impl Plugin for TurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(fire_turrets.before("collision"))
+                .with_system(hit_projectiles.after("collision")),
+        );
+    }
+}
+
+/// Fires projectiles every `interval`, straight ahead or, when it aims, at
+/// the robot it can see.
+#[derive(Component, Debug)]
+pub struct Turret {
+    /// Direction it fires in when it doesn't aim.
+    pub direction: Vec3,
+    pub interval: Timer,
+    /// How far it sees robots to aim at, it fires blindly when `None`.
+    pub sight: Option<f32>,
+    /// Speed of its projectiles.
+    pub speed: f32,
+    /// Circuit output that switches the turret.
+    pub switch: Option<Entity>,
+    /// Whether the circuit turns it on rather than off.
+    pub powered: bool,
+}
+
+impl Turret {
+    fn is_on(&self, sliders: &Query<&Slider>) -> bool {
+        is_switched_on(self.switch, self.powered, sliders)
+    }
+}
+
+/// Kills robots it hits, and breaks on anything solid.
+#[derive(Component, Debug)]
+pub struct Projectile {
+    lifetime: Timer,
+}
+
+#[derive(Bundle)]
+struct ProjectileBundle {
+    #[bundle]
+    sprite_bundle: SpriteBundle,
+    projectile: Projectile,
+    collider: Collider,
+    velocity: Velocity,
+    world_object: WorldObject,
+}
+
+impl ProjectileBundle {
+    fn new(position: Vec3, velocity: Vec3) -> Self {
+        let size = Vec2::splat(BLOCK_SIZE / 5.0);
+        Self {
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::YELLOW,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.truncate().extend(20.0)),
+                ..default()
+            },
+            projectile: Projectile {
+                lifetime: Timer::from_seconds(PROJECTILE_LIFETIME, false),
+            },
+            collider: Collider {
+                kind: ColliderKind::Movable(0.1),
+                size,
+                flags: CollisionFlags::empty(),
+            },
+            velocity: Velocity {
+                linvel: velocity,
+                drag: Vec3::ZERO,
+                ..default()
+            },
+            world_object: WorldObject,
+        }
+    }
+}
+
+/// Whether nothing solid in `obstacles` stands between `from` and `to`,
+/// other than the entities in `ignore`.
+fn in_sight(from: Vec3, to: Vec3, obstacles: &[(Entity, Vec3, Vec2)], ignore: &[Entity]) -> bool {
+    let steps = (from.distance(to) / SIGHT_STEP).ceil() as usize;
+    (1..steps).all(|i| {
+        let point = from.lerp(to, i as f32 / steps as f32).truncate();
+        obstacles.iter().all(|(entity, position, size)| {
+            let offset = (point - position.truncate()).abs();
+            ignore.contains(entity) || offset.x > size.x / 2.0 || offset.y > size.y / 2.0
+        })
+    })
+}
+
+/// The robot turrets aim at.
+type ActiveRobot = (With<Player>, With<Active>);
+
+fn fire_turrets(
+    mut commands: Commands,
+    time: Res<Time>,
+    sliders: Query<&Slider>,
+    robots: Query<(Entity, &Transform), ActiveRobot>,
+    colliders: Query<(Entity, &Transform, &Collider), Without<Projectile>>,
+    mut turrets: Query<(Entity, &Transform, &Collider, &mut Turret)>,
+) {
+    let obstacles: Vec<(Entity, Vec3, Vec2)> = colliders
+        .iter()
+        .filter(|(_, _, collider)| matches!(collider.kind, ColliderKind::Movable(_)))
+        .map(|(entity, transform, collider)| (entity, transform.translation, collider.size))
+        .collect();
+    for (entity, transform, collider, mut turret) in turrets.iter_mut() {
+        if !turret.is_on(&sliders) {
+            turret.interval.reset();
+            continue;
+        }
+        let position = transform.translation;
+        let target = match turret.sight {
+            Some(range) => robots.iter().find(|(robot, robot_transform)| {
+                let robot_position = robot_transform.translation;
+                robot_position.distance(position) <= range
+                    && in_sight(position, robot_position, &obstacles, &[entity, *robot])
+            }),
+            None => None,
+        };
+        // aiming turrets wait until they see a robot
+        if turret.sight.is_some() && target.is_none() {
+            continue;
+        }
+        turret.interval.tick(time.delta());
+        if !turret.interval.just_finished() {
+            continue;
+        }
+        let direction = match target {
+            Some((_, robot_transform)) => (robot_transform.translation - position)
+                .truncate()
+                .normalize_or_zero()
+                .extend(0.0),
+            None => turret.direction,
+        };
+        // start clear of the turret so it doesn't hit itself
+        let start = position + direction * collider.size.max_element();
+        commands.spawn_bundle(ProjectileBundle::new(start, direction * turret.speed));
+    }
+}
+
+fn hit_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut contacts: EventReader<Contact>,
+    mut events: EventWriter<GameEvent>,
+    mut projectiles: Query<(Entity, &mut Projectile)>,
+    colliders: Query<&Collider>,
+    players: Query<(), With<Player>>,
+) {
+    let mut spent = HashSet::new();
+    for contact in contacts.iter() {
+        let (projectile, other) = if projectiles.get(contact.entity).is_ok() {
+            (contact.entity, contact.other)
+        } else if projectiles.get(contact.other).is_ok() {
+            (contact.other, contact.entity)
+        } else {
+            continue;
+        };
+        // sensors like buttons and ladders don't stop projectiles
+        if !matches!(
+            colliders.get(other),
+            Ok(Collider {
+                kind: ColliderKind::Movable(_),
+                ..
+            })
+        ) {
+            continue;
+        }
+        if players.get(other).is_ok() {
+            events.send(GameEvent::Death);
+        }
+        spent.insert(projectile);
+    }
+
+    for (entity, mut projectile) in projectiles.iter_mut() {
+        projectile.lifetime.tick(time.delta());
+        if projectile.lifetime.finished() {
+            spent.insert(entity);
+        }
+    }
+    for entity in spent {
+        commands.entity(entity).despawn();
+    }
+}
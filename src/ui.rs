@@ -1,18 +1,33 @@
-use bevy::prelude::*;
+use bevy::{ecs::schedule::StateError, prelude::*};
 
 use crate::{
-    collide::GameEvent,
-    player::{Latency, Player},
+    drone::Drone,
+    inventory::Inventory,
+    player::{Active, GameInput, Latency, Link, Signal},
     state::GameState,
+    Difficulty,
 };
 
+/// Width of one tick on the input queue strip in pixels.
+const TICK_WIDTH: f32 = 12.0;
+/// Height of one row of inputs arriving in the same tick in pixels.
+const ROW_HEIGHT: f32 = 20.0;
+/// Rows of inputs that fit in the strip, the rest wait out of sight.
+const STRIP_ROWS: usize = 3;
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(button_interaction)
             .add_system_set(SystemSet::on_enter(GameState::Play).with_system(spawn_ui))
-            .add_system_set(SystemSet::on_update(GameState::Play).with_system(update_latency_text))
+            .add_system_set(
+                SystemSet::on_update(GameState::Play)
+                    .with_system(update_latency_text)
+                    .with_system(update_signal_text)
+                    .with_system(update_inventory_text)
+                    .with_system(update_input_strip),
+            )
             .add_system_set(
                 SystemSet::on_update(GameState::Play).with_system(pause_button_interaction),
             );
@@ -31,6 +46,21 @@ struct LatencyText;
 #[derive(Component)]
 struct LatencyImage;
 
+#[derive(Component)]
+struct SignalText;
+
+/// Keys the active robot carries.
+#[derive(Component)]
+struct InventoryText;
+
+/// Strip along the bottom of the screen showing the queued inputs.
+#[derive(Component)]
+struct InputStrip;
+
+/// One of the icons on the input strip, kept around and reused.
+#[derive(Component)]
+struct InputIcon;
+
 fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     let font = asset_server.load("VT323-Regular.ttf");
 
@@ -71,7 +101,7 @@ fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                     text: Text::with_section(
                         "0ms",
                         TextStyle {
-                            font,
+                            font: font.clone(),
                             font_size: 50.0,
                             color: Color::BLACK,
                         },
@@ -83,8 +113,75 @@ fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ..default()
                 })
                 .insert(LatencyText);
+
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        align_self: AlignSelf::Center,
+                        margin: Rect {
+                            left: Val::Px(15.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    text: Text::with_section(
+                        "0% loss",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 30.0,
+                            color: Color::BLACK,
+                        },
+                        default(),
+                    ),
+                    ..default()
+                })
+                .insert(SignalText);
+
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        align_self: AlignSelf::Center,
+                        margin: Rect {
+                            left: Val::Px(15.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font,
+                            font_size: 30.0,
+                            color: Color::BLACK,
+                        },
+                        default(),
+                    ),
+                    ..default()
+                })
+                .insert(InventoryText);
         });
 
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(TICK_WIDTH), Val::Px(70.0)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(15.0),
+                    left: Val::Px(15.0),
+                    ..default()
+                },
+                border: Rect {
+                    left: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.25).into(),
+            ..default()
+        })
+        .insert(InputStrip);
+
     commands
         .spawn_bundle(ButtonBundle {
             style: Style {
@@ -129,7 +226,12 @@ fn pause_button_interaction(
 ) {
     for interaction in interaction_query.iter() {
         if interaction == &Interaction::Clicked {
-            state.push(GameState::Pause).unwrap();
+            // pausing by key may have queued it already this frame
+            if let Err(err) = state.push(GameState::Pause) {
+                if !matches!(err, StateError::StateAlreadyQueued) {
+                    warn!("Could not pause: {:?}", err);
+                }
+            }
         }
     }
 }
@@ -139,12 +241,11 @@ fn update_latency_text(
     latency: Res<Latency>,
     mut text_query: Query<&mut Text, With<LatencyText>>,
     mut image_query: Query<&mut UiImage, With<LatencyImage>>,
-    mut player_query: Query<&mut Player>,
-    mut events: EventWriter<GameEvent>,
+    player_query: Query<&Link, With<Active>>,
 ) {
     let mut text = text_query.single_mut();
     let mut image = image_query.single_mut();
-    let player = match player_query.get_single_mut() {
+    let player = match player_query.get_single() {
         Ok(p) => p,
         Err(_) => {
             image.0 = asset_server.load("wifi_1.png");
@@ -153,11 +254,15 @@ fn update_latency_text(
         }
     };
 
-    match player.latency {
-        0..=7 => image.0 = asset_server.load("wifi_3.png"),
-        8..=24 => image.0 = asset_server.load("wifi_2.png"),
-        25..=40 => image.0 = asset_server.load("wifi_1.png"),
-        _ => events.send(GameEvent::Death),
+    match player.signal {
+        Signal::Connected if player.latency <= 7 => image.0 = asset_server.load("wifi_3.png"),
+        Signal::Connected => image.0 = asset_server.load("wifi_2.png"),
+        Signal::Degraded => image.0 = asset_server.load("wifi_1.png"),
+        Signal::Disconnected => {
+            image.0 = asset_server.load("wifi_1.png");
+            text.sections[0].value = "No signal".to_string();
+            return;
+        }
     }
 
     text.sections[0].value = format!(
@@ -165,3 +270,135 @@ fn update_latency_text(
         (latency.0.iter().sum::<i32>() as f32 / latency.0.len() as f32) * player.latency as f32
     );
 }
+
+fn update_signal_text(
+    latency: Res<Latency>,
+    mut text_query: Query<&mut Text, With<SignalText>>,
+    player_query: Query<(&Link, Option<&Drone>), With<Active>>,
+) {
+    let mut text = text_query.single_mut();
+    let tick = latency.0.iter().sum::<i32>() as f32 / latency.0.len() as f32;
+    text.sections[0].value = match player_query.get_single() {
+        Ok((player, drone)) => {
+            let mut value = format!(
+                "{:.0}% loss\n\u{b1}{:.0}ms",
+                player.loss * 100.0,
+                tick * player.jitter as f32
+            );
+            if let Some(drone) = drone {
+                value += &format!("\n{:.0}% battery", drone.battery / drone.capacity * 100.0);
+            }
+            value
+        }
+        Err(_) => "100% loss".to_string(),
+    };
+}
+
+fn update_inventory_text(
+    mut text_query: Query<&mut Text, With<InventoryText>>,
+    player_query: Query<&Inventory, With<Active>>,
+) {
+    let mut text = text_query.single_mut();
+    text.sections[0].value = match player_query.get_single() {
+        Ok(inventory) if !inventory.keys.is_empty() => {
+            format!("keys: {}", inventory.keys.join(", "))
+        }
+        _ => String::new(),
+    };
+}
+
+type StripQuery<'a> = (Entity, &'a mut Style, Option<&'a Children>);
+
+type IconQuery<'a> = (&'a mut Style, &'a mut Text, &'a mut Visibility);
+
+fn update_input_strip(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
+    mut strip_query: Query<StripQuery, (With<InputStrip>, Without<InputIcon>)>,
+    mut icons: Query<IconQuery, (With<InputIcon>, Without<InputStrip>)>,
+    player_query: Query<&Link, With<Active>>,
+) {
+    let (strip, mut style, children) = strip_query.single_mut();
+    if !difficulty.show_queue {
+        style.display = Display::None;
+        return;
+    }
+    style.display = Display::Flex;
+    let player = player_query.get_single().ok();
+
+    // the robot is at the left end, inputs slide toward it as their ticks run out
+    let mut stacked: Vec<usize> = vec![];
+    let mut shown = vec![];
+    for (ticks, input) in player.into_iter().flat_map(Link::pending) {
+        let row = stacked.iter().filter(|&&t| t == ticks).count();
+        let icon = match input {
+            GameInput::Left => "<",
+            GameInput::Right => ">",
+            GameInput::Jump => "^",
+            GameInput::Up => "+",
+            GameInput::Down => "-",
+            GameInput::Interact => "*",
+            // holding jump is sent every tick, showing it would hide the rest
+            GameInput::HoldJump => continue,
+        };
+        stacked.push(ticks);
+        if row < STRIP_ROWS {
+            shown.push((ticks, row, icon));
+        }
+    }
+
+    // long enough for an input to arrive as late as it can, and for the ones
+    // sent before the latency went down
+    let reach = shown
+        .iter()
+        .map(|&(ticks, ..)| ticks)
+        .chain(player.map(|player| player.latency + player.jitter))
+        .max()
+        .unwrap_or(0);
+    style.size.width = Val::Px((reach + 1) as f32 * TICK_WIDTH);
+
+    let mut pool = children.into_iter().flat_map(|children| children.iter());
+    let font = asset_server.load("VT323-Regular.ttf");
+    for (ticks, row, icon) in shown {
+        let position = Rect {
+            left: Val::Px(ticks as f32 * TICK_WIDTH),
+            top: Val::Px(row as f32 * ROW_HEIGHT),
+            ..default()
+        };
+        if let Some((mut style, mut text, mut visibility)) =
+            pool.next().and_then(|&entity| icons.get_mut(entity).ok())
+        {
+            style.position = position;
+            text.sections[0].value = icon.to_string();
+            visibility.is_visible = true;
+            continue;
+        }
+        let icon = commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    ..default()
+                },
+                text: Text::with_section(
+                    icon,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                    default(),
+                ),
+                ..default()
+            })
+            .insert(InputIcon)
+            .id();
+        commands.entity(strip).add_child(icon);
+    }
+    for &entity in pool {
+        if let Ok((_, _, mut visibility)) = icons.get_mut(entity) {
+            visibility.is_visible = false;
+        }
+    }
+}
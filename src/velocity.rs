@@ -1,27 +1,173 @@
 use bevy::prelude::*;
 
-use crate::state::GameState;
+use crate::{
+    collide::{CollisionFlags, Contact},
+    slider::Slider,
+    state::GameState,
+};
+
+/// Acceleration of normal gravity in units/s², the same 100 units/s per frame
+/// it used to add at 60 fps.
+pub const GRAVITY: f32 = 6000.0;
 
 pub struct VelocityPlugin;
 
 impl Plugin for VelocityPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_update(GameState::Play).with_system(update_gravity));
+        app.insert_resource(WorldGravity::default()).add_system_set(
+            SystemSet::on_update(GameState::Play)
+                .with_system(update_gravity_switches)
+                .with_system(
+                    update_gravity
+                        .label("gravity")
+                        .after(update_gravity_switches)
+                        .before("collision"),
+                )
+                .with_system(bounce_springboards.after("collision")),
+        );
     }
 }
 
-#[derive(Component, Debug)]
-pub struct Gravity(f32);
+/// Gravity currently pulling on a body. It is set every frame from the
+/// gravity zone the body is in, or from [`WorldGravity`] outside of zones.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Gravity(pub Vec3);
 
 impl Default for Gravity {
     fn default() -> Self {
-        Gravity(100.0)
+        Gravity(-Vec3::Y * GRAVITY)
+    }
+}
+
+impl Gravity {
+    /// Unit vector pointing "down", snapped to the axis gravity mostly pulls along.
+    pub fn down(&self) -> Vec3 {
+        if self.0.x.abs() > self.0.y.abs() {
+            Vec3::X * self.0.x.signum()
+        } else if self.0.y > 0.0 {
+            Vec3::Y
+        } else {
+            -Vec3::Y
+        }
+    }
+
+    /// Direction the right input walks in. This stays screen right while
+    /// gravity is vertical and becomes screen up when it is sideways.
+    pub fn right(&self) -> Vec3 {
+        if self.is_sideways() {
+            Vec3::Y
+        } else {
+            Vec3::X
+        }
     }
+
+    pub fn is_sideways(&self) -> bool {
+        self.down().x != 0.0
+    }
+
+    /// The side of a collider that a body stands on.
+    pub fn floor(&self) -> CollisionFlags {
+        CollisionFlags::facing(self.down())
+    }
+
+    /// Rotation that turns a sprite drawn standing on the ground toward the
+    /// current down.
+    pub fn rotation(&self) -> Quat {
+        let down = self.down();
+        Quat::from_rotation_z(down.x.atan2(-down.y))
+    }
+}
+
+/// Gravity of the whole level outside of gravity zones.
+#[derive(Debug)]
+pub struct WorldGravity {
+    pub base: Vec3,
+    pub flipped: bool,
+}
+
+impl Default for WorldGravity {
+    fn default() -> Self {
+        Self {
+            base: Gravity::default().0,
+            flipped: false,
+        }
+    }
+}
+
+impl WorldGravity {
+    pub fn current(&self) -> Vec3 {
+        if self.flipped {
+            -self.base
+        } else {
+            self.base
+        }
+    }
+}
+
+/// A region of the level with its own gravity.
+#[derive(Component, Debug)]
+pub struct GravityZone {
+    pub size: Vec2,
+    pub gravity: Vec3,
+}
+
+/// Circuit output that flips the gravity of the whole level while active.
+#[derive(Component, Debug)]
+pub struct GravitySwitch;
+
+fn update_gravity_switches(
+    switches: Query<&Slider, With<GravitySwitch>>,
+    mut world_gravity: ResMut<WorldGravity>,
+) {
+    // every active switch flips gravity once more
+    world_gravity.flipped = switches.iter().filter(|switch| switch.activated).count() % 2 == 1;
 }
 
-fn update_gravity(mut query: Query<(&mut Velocity, &Gravity)>) {
-    for (mut velocity, gravity) in query.iter_mut() {
-        velocity.linvel.y -= gravity.0;
+fn update_gravity(
+    mut query: Query<(&Transform, &mut Velocity, &mut Gravity)>,
+    zones: Query<(&Transform, &GravityZone)>,
+    world_gravity: Res<WorldGravity>,
+) {
+    for (transform, mut velocity, mut gravity) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        gravity.0 = zones
+            .iter()
+            .find(|(zone_transform, zone)| {
+                let offset = (position - zone_transform.translation.truncate()).abs();
+                offset.x <= zone.size.x / 2.0 && offset.y <= zone.size.y / 2.0
+            })
+            .map(|(_, zone)| zone.gravity)
+            .unwrap_or_else(|| world_gravity.current());
+        velocity.apply_force(gravity.0);
+    }
+}
+
+/// Launches bodies that land on it away from it.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Springboard {
+    /// Speed the body is launched at.
+    pub impulse: f32,
+}
+
+fn bounce_springboards(
+    mut contacts: EventReader<Contact>,
+    springboards: Query<&Springboard>,
+    mut bodies: Query<(&mut Velocity, Option<&Gravity>)>,
+) {
+    for contact in contacts.iter() {
+        let springboard = match springboards.get(contact.other) {
+            Ok(springboard) => springboard,
+            _ => continue,
+        };
+        let (mut velocity, gravity) = match bodies.get_mut(contact.entity) {
+            Ok(body) => body,
+            _ => continue,
+        };
+        // only bounce bodies landing on top, not ones bumping into the side
+        let gravity = gravity.copied().unwrap_or_default();
+        if contact.side.contains(gravity.floor()) {
+            velocity.apply_impulse(-gravity.down() * springboard.impulse);
+        }
     }
 }
 
@@ -29,6 +175,10 @@ fn update_gravity(mut query: Query<(&mut Velocity, &Gravity)>) {
 pub struct Velocity {
     pub linvel: Vec3,
     pub drag: Vec3,
+    /// Velocity change waiting for the next physics step.
+    pub impulse: Vec3,
+    /// Acceleration (units/s²) integrated over the next physics step.
+    pub force: Vec3,
 }
 
 impl Default for Velocity {
@@ -36,6 +186,149 @@ impl Default for Velocity {
         Velocity {
             linvel: Vec3::ZERO,
             drag: Vec3::splat(0.95),
+            impulse: Vec3::ZERO,
+            force: Vec3::ZERO,
+        }
+    }
+}
+
+impl Velocity {
+    /// Changes the velocity by `impulse` all at once when the next physics step
+    /// starts, so it isn't lost when the step recomputes `linvel` from the
+    /// resolved positions. Drag then eats into it as usual.
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        self.impulse += impulse;
+    }
+
+    /// Accelerates the body by `force` over the whole next physics step.
+    pub fn apply_force(&mut self, force: Vec3) {
+        self.force += force;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::Events;
+
+    use super::*;
+    use crate::{
+        collide::{simulate, Body, Collider, ColliderKind},
+        map::BLOCK_SIZE,
+    };
+
+    const DIMENSIONS: (f32, f32) = (16.0 * BLOCK_SIZE, 16.0 * BLOCK_SIZE);
+
+    fn falling_body(velocity: Velocity) -> Body {
+        Body {
+            entity: Entity::from_raw(0),
+            position: Vec3::new(5.0 * BLOCK_SIZE, 5.0 * BLOCK_SIZE, 0.0),
+            collider: Collider {
+                size: Vec2::splat(BLOCK_SIZE / 2.0),
+                kind: ColliderKind::Movable(1.0),
+                flags: CollisionFlags::empty(),
+            },
+            velocity: Some(velocity),
+            surface: None,
+            gravity: None,
         }
     }
+
+    #[test]
+    fn impulse_lands_in_full_then_drag_applies() {
+        let mut velocity = Velocity {
+            drag: Vec3::new(2.0, 3.0, 0.0),
+            ..default()
+        };
+        velocity.apply_impulse(Vec3::new(600.0, 1200.0, 0.0));
+        let mut bodies = [falling_body(velocity)];
+
+        simulate(&mut bodies, DIMENSIONS, 0.1);
+
+        let velocity = bodies[0].velocity.as_ref().unwrap();
+        // 600 - 600 * 2 * 0.1 and 1200 - 1200 * 3 * 0.1
+        assert!(
+            (velocity.linvel.x - 480.0).abs() < 1e-3,
+            "{}",
+            velocity.linvel
+        );
+        assert!(
+            (velocity.linvel.y - 840.0).abs() < 1e-3,
+            "{}",
+            velocity.linvel
+        );
+        assert_eq!(velocity.impulse, Vec3::ZERO);
+        assert_eq!(bodies[0].position.y, 5.0 * BLOCK_SIZE + 120.0);
+    }
+
+    #[test]
+    fn force_is_integrated_over_the_step_then_drag_applies() {
+        let mut velocity = Velocity {
+            drag: Vec3::new(0.0, 0.5, 0.0),
+            ..default()
+        };
+        velocity.apply_force(Vec3::new(0.0, -GRAVITY, 0.0));
+        let mut bodies = [falling_body(velocity)];
+
+        simulate(&mut bodies, DIMENSIONS, 0.1);
+
+        let velocity = bodies[0].velocity.as_ref().unwrap();
+        // -6000 * 0.1 = -600, then -600 - -600 * 0.5 * 0.1
+        assert!(
+            (velocity.linvel.y + 570.0).abs() < 1e-3,
+            "{}",
+            velocity.linvel
+        );
+        assert_eq!(velocity.force, Vec3::ZERO);
+    }
+
+    /// Velocity of a body with a robot's drag after it lands on a springboard
+    /// from `side`, before the next physics step.
+    fn bounce(side: CollisionFlags) -> Velocity {
+        let mut app = App::new();
+        app.add_event::<Contact>().add_system(bounce_springboards);
+        let springboard = app
+            .world
+            .spawn()
+            .insert(Springboard { impulse: 2500.0 })
+            .id();
+        let body = app
+            .world
+            .spawn()
+            .insert(Velocity {
+                drag: Vec3::new(20.0, 5.0, 0.0),
+                ..default()
+            })
+            .insert(Gravity::default())
+            .id();
+        app.world
+            .get_resource_mut::<Events<Contact>>()
+            .unwrap()
+            .send(Contact {
+                entity: body,
+                other: springboard,
+                side,
+                kind: ColliderKind::Movable(f32::INFINITY),
+                velocity: Vec3::new(0.0, -800.0, 0.0),
+            });
+        app.update();
+        app.world.get::<Velocity>(body).unwrap().clone()
+    }
+
+    #[test]
+    fn springboard_launches_bodies_landing_on_it() {
+        let mut bodies = [falling_body(bounce(CollisionFlags::BOTTOM))];
+
+        simulate(&mut bodies, DIMENSIONS, 0.1);
+
+        // the launch loses a step of drag like any other speed,
+        // 2500 - 2500 * 5 * 0.1
+        let velocity = bodies[0].velocity.as_ref().unwrap();
+        assert!(
+            (velocity.linvel.y - 1250.0).abs() < 1e-2,
+            "{}",
+            velocity.linvel
+        );
+        assert_eq!(velocity.linvel.x, 0.0);
+        assert_eq!(bounce(CollisionFlags::LEFT).impulse, Vec3::ZERO);
+    }
 }